@@ -2,3 +2,58 @@ use anchor_lang::prelude::*;
 
 #[constant]
 pub const SEED: &str = "anchor";
+
+// Exponent of strikes reported in events, matching the usual pyth price exponent
+#[constant]
+pub const PRICE_EXPONENT: i32 = -8;
+
+// Widest confidence interval, as basis points of the price, accepted for a mark until
+// the admin sets another
+#[constant]
+pub const DEFAULT_MAX_CONF_BPS: u16 = 200;
+
+// Widest confidence interval the admin may accept for marks, and so the widest
+// tolerance an option may choose
+#[constant]
+pub const MAX_CONF_BPS: u16 = 1_000;
+
+// Seconds before expiry a price may be published and still settle an option
+#[constant]
+pub const DEFAULT_MARK_WINDOW: i64 = 30 * 60;
+
+// Widest window an option may choose, and so the widest a mark may be set in
+#[constant]
+pub const MAX_MARK_WINDOW: i64 = 24 * 60 * 60;
+
+// Seconds before expiry in which marks are also sampled into the TWAP
+#[constant]
+pub const TWAP_WINDOW: i64 = 30 * 60;
+
+// Fewest samples the TWAP needs before it can settle an option
+#[constant]
+pub const MIN_TWAP_SAMPLES: u32 = 3;
+
+// Seconds after expiry in which a mark may still be improved, after which it is final
+#[constant]
+pub const MARK_CHALLENGE_PERIOD: i64 = 15 * 60;
+
+// Seconds after expiry without a usable mark before an option lapses, returning the
+// collateral to the seller as if it expired out of the money
+#[constant]
+pub const SETTLEMENT_GRACE_PERIOD: i64 = 3 * 24 * 60 * 60;
+
+// Highest protocol fee the admin can set, in basis points
+#[constant]
+pub const MAX_FEE_BPS: u16 = 1_000;
+
+// Most feeds the config can allow options to be written on
+#[constant]
+pub const MAX_ALLOWED_FEEDS: usize = 16;
+
+// Maximum age in seconds of the price used to exercise an american option before expiry
+#[constant]
+pub const EXERCISE_PRICE_MAX_AGE: u64 = 60;
+
+// Maximum length in bytes of the reason given when cancelling an option
+#[constant]
+pub const MAX_CANCEL_REASON_LEN: usize = 64;
@@ -20,4 +20,64 @@ pub enum ErrorCode {
     PriceIrrelevant,
     #[msg("Option not marked")]
     OptionNotMarked,
+    #[msg("Premium is below the asking premium")]
+    PremiumTooLow,
+    #[msg("Premium token account was not provided")]
+    PremiumAccountMissing,
+    #[msg("Option token account was not provided")]
+    OptionTokenMissing,
+    #[msg("Option position is tokenized")]
+    OptionIsTokenized,
+    #[msg("Mint extension is not supported")]
+    UnsupportedMintExtension,
+    #[msg("Math overflow")]
+    MathOverflow,
+    #[msg("Quote token account was not provided")]
+    QuoteAccountMissing,
+    #[msg("Price update account was not provided")]
+    PriceUpdateMissing,
+    #[msg("Amounts must divide evenly into contracts")]
+    InvalidContracts,
+    #[msg("Quantity exceeds the open contracts")]
+    InvalidQuantity,
+    #[msg("Not enough contracts are available from this writer")]
+    ContractsUnavailable,
+    #[msg("Cancel reason is too long")]
+    CancelReasonTooLong,
+    #[msg("Price confidence interval is too wide")]
+    ConfidenceTooWide,
+    #[msg("Mark window is out of range")]
+    InvalidMarkWindow,
+    #[msg("Not enough price samples for the TWAP")]
+    TwapNotReady,
+    #[msg("Option lapsed without a mark")]
+    OptionLapsed,
+    #[msg("Mark is final and can no longer be updated")]
+    MarkFinalized,
+    #[msg("Mark is still in its challenge period")]
+    MarkNotFinal,
+    #[msg("Expiry account was not provided")]
+    ExpiryAccountMissing,
+    #[msg("Mark is still needed by open options")]
+    MarkInUse,
+    #[msg("Fee is above the maximum")]
+    InvalidFee,
+    #[msg("Too many allowed feeds")]
+    TooManyFeeds,
+    #[msg("Feed is not allowed")]
+    FeedNotAllowed,
+    #[msg("Signer is not the pending admin")]
+    NotPendingAdmin,
+    #[msg("Treasury token account was not provided")]
+    TreasuryAccountMissing,
+    #[msg("Instruction is paused")]
+    Paused,
+    #[msg("Only the whole position can be transferred unless the option is tokenized")]
+    PartialTransferNotSupported,
+    #[msg("Confidence tolerance is out of range")]
+    InvalidConfTolerance,
+    #[msg("Signer is not the program upgrade authority")]
+    NotUpgradeAuthority,
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
 }
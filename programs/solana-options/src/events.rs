@@ -0,0 +1,200 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{ExerciseStyle, OptionKind, PriceSource, SettlementType};
+
+#[event]
+pub struct OptionCreated {
+    pub option: Pubkey,
+    pub kind: OptionKind,
+    pub seller: Pubkey,
+    pub buyer: Option<Pubkey>,
+    pub mint_base: Pubkey,
+    pub mint_quote: Pubkey,
+    pub mint_premium: Pubkey,
+    pub amount_base: u64,
+    pub amount_quote: u64,
+    pub amount_premium_ask: u64,
+    pub settlement: SettlementType,
+    pub exercise_style: ExerciseStyle,
+    pub contracts: u64,
+    pub max_conf_bps: u16,
+    pub mark_window: i64,
+    pub price_source: PriceSource,
+    pub strike: i64,
+    pub timestamp_expiry: i64,
+    pub feed_id: [u8; 32],
+}
+
+#[event]
+pub struct OptionBought {
+    pub option: Pubkey,
+    pub kind: OptionKind,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub payer: Pubkey,
+    pub mint_premium: Pubkey,
+    pub amount_premium: u64,
+    // Part of the premium taken as protocol fee
+    pub amount_fee: u64,
+    pub is_premium_to_seller: bool,
+}
+
+#[event]
+pub struct OptionMarked {
+    pub expiry: Pubkey,
+    pub feed_id: [u8; 32],
+    pub timestamp_expiry: i64,
+    pub price: i64,
+    pub conf: u64,
+    pub exponent: i32,
+    pub publish_time: i64,
+}
+
+#[event]
+pub struct OptionExercised {
+    pub option: Pubkey,
+    pub kind: OptionKind,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub mint: Pubkey,
+    pub strike: i64,
+    pub price: i64,
+    pub contracts: u64,
+    pub amount_seller: u64,
+    pub amount_buyer: u64,
+    // Part of the settlement taken as protocol fee
+    pub amount_fee: u64,
+    pub amount_quote_paid: u64,
+}
+
+#[event]
+pub struct OptionClosed {
+    pub option: Pubkey,
+    pub kind: OptionKind,
+    pub seller: Pubkey,
+    pub amount_collateral: u64,
+    pub amount_premium: u64,
+    pub amount_quote: u64,
+    // Closed by the grace period fallback without a price to settle at
+    pub is_lapsed: bool,
+}
+
+#[event]
+pub struct OptionCancelled {
+    pub option: Pubkey,
+    pub kind: OptionKind,
+    pub seller: Pubkey,
+    pub amount_collateral: u64,
+    pub reason: String,
+    pub timestamp: i64,
+}
+
+#[event]
+pub struct PositionTransferred {
+    pub option: Pubkey,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub contracts: u64,
+}
+
+#[event]
+pub struct MarkClosed {
+    pub expiry: Pubkey,
+    pub feed_id: [u8; 32],
+    pub timestamp_expiry: i64,
+    pub payer: Pubkey,
+}
+
+#[event]
+pub struct SeriesCreated {
+    pub series: Pubkey,
+    pub mint_base: Pubkey,
+    pub mint_quote: Pubkey,
+    pub mint_option: Pubkey,
+    pub amount_base: u64,
+    pub amount_quote: u64,
+    pub strike: i64,
+    pub timestamp_expiry: i64,
+    pub feed_id: [u8; 32],
+    pub exercise_style: ExerciseStyle,
+    pub max_conf_bps: u16,
+    pub mark_window: i64,
+}
+
+#[event]
+pub struct SeriesWritten {
+    pub series: Pubkey,
+    pub seller: Pubkey,
+    pub contracts: u64,
+    pub mint_premium: Pubkey,
+    pub amount_premium_ask: u64,
+}
+
+#[event]
+pub struct SeriesBought {
+    pub series: Pubkey,
+    pub seller: Pubkey,
+    pub buyer: Pubkey,
+    pub payer: Pubkey,
+    pub contracts: u64,
+    pub mint_premium: Pubkey,
+    pub amount_premium: u64,
+    pub amount_fee: u64,
+}
+
+#[event]
+pub struct SeriesExercised {
+    pub series: Pubkey,
+    pub buyer: Pubkey,
+    pub contracts: u64,
+    pub strike: i64,
+    pub price: i64,
+    pub amount_buyer: u64,
+    pub amount_fee: u64,
+}
+
+#[event]
+pub struct SeriesWithdrawn {
+    pub series: Pubkey,
+    pub seller: Pubkey,
+    pub contracts_unsold: u64,
+    pub contracts_sold: u64,
+    pub amount_base: u64,
+    pub is_lapsed: bool,
+}
+
+#[event]
+pub struct ConfigUpdated {
+    pub config: Pubkey,
+    pub admin: Pubkey,
+    pub fee_bps_premium: u16,
+    pub fee_bps_settlement: u16,
+    pub mark_window: i64,
+    pub max_conf_bps: u16,
+    pub allowed_feeds: Vec<[u8; 32]>,
+    pub is_write_paused: bool,
+    pub is_buy_paused: bool,
+    pub is_mark_paused: bool,
+}
+
+#[event]
+pub struct AdminProposed {
+    pub config: Pubkey,
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+}
+
+#[event]
+pub struct AdminAccepted {
+    pub config: Pubkey,
+    pub previous_admin: Pubkey,
+    pub admin: Pubkey,
+}
+
+#[event]
+pub struct TreasuryWithdrawn {
+    pub config: Pubkey,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
@@ -0,0 +1,31 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::AdminAccepted;
+use crate::state::Config;
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    pub pending_admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        constraint = config.pending_admin == Some(pending_admin.key()) @ ErrorCode::NotPendingAdmin,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+pub fn handle_accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let previous_admin = ctx.accounts.config.admin;
+    ctx.accounts.config.admin = ctx.accounts.pending_admin.key();
+    ctx.accounts.config.pending_admin = None;
+
+    emit!(AdminAccepted {
+        config: ctx.accounts.config.key(),
+        previous_admin,
+        admin: ctx.accounts.config.admin,
+    });
+
+    Ok(())
+}
@@ -1,25 +1,30 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{transfer_checked, Mint, Token, TokenAccount, TransferChecked},
+    token_interface::{
+        mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
 
 use crate::error::ErrorCode;
-use crate::state::CoveredCall;
+use crate::events::OptionBought;
+use crate::math::calc_fee;
+use crate::mint::transfer_fee_to_treasury;
+use crate::state::{Config, CoveredCall, OptionKind};
+use crate::ExpiryData;
 
 #[derive(Accounts)]
 #[instruction(amount_premium: u64)]
 pub struct Buy<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
-    #[account(mut, constraint = buyer.key() == data.buyer)]
+    #[account(mut, constraint = data.buyer.is_none() || data.buyer == Some(buyer.key()))]
     pub buyer: SystemAccount<'info>,
     #[account(
         mut,
         seeds = [
             "covered-call".as_bytes(), 
             data.seller.as_ref(),
-            buyer.key().as_ref(),
             data.mint_base.as_ref(),
             data.mint_quote.as_ref(),
             &data.amount_base.to_le_bytes(),
@@ -29,27 +34,72 @@ pub struct Buy<'info> {
         bump = data.bump,
     )]
     pub data: Account<'info, CoveredCall>,
-    #[account( constraint = mint_premium.key() == data.mint_base)]
-    pub mint_premium: Account<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ExpiryData::INIT_SPACE,
+        seeds = [
+          "expiry-meta".as_bytes(),
+          data.feed_id.as_ref(),
+          &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub expiry: Account<'info, ExpiryData>,
+    #[account( constraint = mint_premium.key() == data.mint_premium)]
+    pub mint_premium: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         constraint = ata_payer_premium.amount >= amount_premium,
         associated_token::mint = mint_premium,
         associated_token::authority = payer,
+        associated_token::token_program = token_program_premium,
     )]
-    pub ata_payer_premium: Account<'info, TokenAccount>, // This already exists because we enforce it to be base
+    pub ata_payer_premium: InterfaceAccount<'info, TokenAccount>,
     #[account(
-        mut,
+        init_if_needed,
+        payer = payer,
         associated_token::mint = mint_premium,
         associated_token::authority = data,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_vault_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_premium,
+        associated_token::authority = data.seller,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_seller_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, constraint = data.mint_option == Some(mint_option.key()))]
+    pub mint_option: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_option,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_buyer_option: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_premium,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_premium,
     )]
-    pub ata_vault_premium: Account<'info, TokenAccount>,
+    pub ata_treasury_premium: Option<InterfaceAccount<'info, TokenAccount>>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_premium: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
 pub fn handle_buy(ctx: Context<Buy>, amount_premium: u64) -> Result<()> {
+    require!(!ctx.accounts.config.is_buy_paused, ErrorCode::Paused);
+
     let clock = Clock::get()?;
 
     require!(
@@ -61,22 +111,101 @@ pub fn handle_buy(ctx: Context<Buy>, amount_premium: u64) -> Result<()> {
         ctx.accounts.data.amount_premium.is_none(),
         ErrorCode::OptionAlreadyBought
     );
+
+    require!(
+        amount_premium >= ctx.accounts.data.amount_premium_ask,
+        ErrorCode::PremiumTooLow
+    );
     ctx.accounts.data.amount_premium = Some(amount_premium);
+    // Keep the mark for this expiry until the option settles
+    ctx.accounts.expiry.retain(
+        ctx.accounts.payer.key(),
+        ctx.accounts.data.feed_id,
+        ctx.bumps.expiry,
+    )?;
+    // Open options are assigned to whoever buys them first
+    ctx.accounts.data.buyer = Some(ctx.accounts.buyer.key());
+
+    // Protocol fee is taken out of the premium
+    let amount_fee = calc_fee(amount_premium, ctx.accounts.config.fee_bps_premium);
+    transfer_fee_to_treasury(
+        &ctx.accounts.token_program_premium,
+        &ctx.accounts.mint_premium,
+        ctx.accounts.ata_payer_premium.to_account_info(),
+        ctx.accounts.ata_treasury_premium.as_ref(),
+        ctx.accounts.payer.to_account_info(),
+        &[],
+        amount_fee,
+    )?;
+
+    // Transfer premium to seller or hold it in the vault until close
+    let ata_premium = if ctx.accounts.data.is_premium_to_seller {
+        ctx.accounts.ata_seller_premium.as_ref()
+    } else {
+        ctx.accounts.ata_vault_premium.as_ref()
+    }
+    .ok_or(ErrorCode::PremiumAccountMissing)?;
 
-    // Transfer premium in base to vault
     transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
+            ctx.accounts.token_program_premium.to_account_info(),
             TransferChecked {
                 from: ctx.accounts.ata_payer_premium.to_account_info(),
-                to: ctx.accounts.ata_vault_premium.to_account_info(),
+                to: ata_premium.to_account_info(),
                 mint: ctx.accounts.mint_premium.to_account_info(),
                 authority: ctx.accounts.payer.to_account_info(),
             },
         ),
-        amount_premium,
+        amount_premium - amount_fee,
         ctx.accounts.mint_premium.decimals,
     )?;
 
+    // Mint the long position to the buyer so it can be traded
+    if ctx.accounts.data.mint_option.is_some() {
+        let (Some(mint_option), Some(ata_buyer_option)) = (
+            ctx.accounts.mint_option.as_ref(),
+            ctx.accounts.ata_buyer_option.as_ref(),
+        ) else {
+            return err!(ErrorCode::OptionTokenMissing);
+        };
+
+        let seeds = [
+            "covered-call".as_bytes(),
+            ctx.accounts.data.seller.as_ref(),
+            ctx.accounts.data.mint_base.as_ref(),
+            ctx.accounts.data.mint_quote.as_ref(),
+            &ctx.accounts.data.amount_base.to_le_bytes(),
+            &ctx.accounts.data.amount_quote.to_le_bytes(),
+            &ctx.accounts.data.timestamp_expiry.to_le_bytes(),
+            &[ctx.accounts.data.bump],
+        ];
+        let signer = &[&seeds[..]];
+
+        mint_to(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                MintTo {
+                    mint: mint_option.to_account_info(),
+                    to: ata_buyer_option.to_account_info(),
+                    authority: ctx.accounts.data.to_account_info(),
+                },
+                signer,
+            ),
+            ctx.accounts.data.contracts,
+        )?;
+    }
+
+    emit!(OptionBought {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Call,
+        seller: ctx.accounts.data.seller,
+        buyer: ctx.accounts.buyer.key(),
+        payer: ctx.accounts.payer.key(),
+        mint_premium: ctx.accounts.mint_premium.key(),
+        amount_premium,
+        amount_fee,
+        is_premium_to_seller: ctx.accounts.data.is_premium_to_seller,
+    });
+
     Ok(())
 }
@@ -0,0 +1,163 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::error::ErrorCode;
+use crate::events::OptionBought;
+use crate::math::calc_fee;
+use crate::mint::transfer_fee_to_treasury;
+use crate::state::{CashSecuredPut, Config, OptionKind};
+use crate::ExpiryData;
+
+#[derive(Accounts)]
+#[instruction(amount_premium: u64)]
+pub struct BuyPut<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, constraint = data.buyer.is_none() || data.buyer == Some(buyer.key()))]
+    pub buyer: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "cash-secured-put".as_bytes(),
+            data.seller.as_ref(),
+            data.mint_base.as_ref(),
+            data.mint_quote.as_ref(),
+            &data.amount_base.to_le_bytes(),
+            &data.amount_quote.to_le_bytes(),
+            &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = data.bump,
+    )]
+    pub data: Account<'info, CashSecuredPut>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ExpiryData::INIT_SPACE,
+        seeds = [
+          "expiry-meta".as_bytes(),
+          data.feed_id.as_ref(),
+          &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub expiry: Account<'info, ExpiryData>,
+    #[account( constraint = mint_premium.key() == data.mint_premium)]
+    pub mint_premium: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = ata_payer_premium.amount >= amount_premium,
+        associated_token::mint = mint_premium,
+        associated_token::authority = payer,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_payer_premium: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_premium,
+        associated_token::authority = data,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_vault_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_premium,
+        associated_token::authority = data.seller,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_seller_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_premium,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_treasury_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program_premium: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_buy_put(ctx: Context<BuyPut>, amount_premium: u64) -> Result<()> {
+    require!(!ctx.accounts.config.is_buy_paused, ErrorCode::Paused);
+
+    let clock = Clock::get()?;
+
+    require!(
+        clock.unix_timestamp <= ctx.accounts.data.timestamp_expiry,
+        ErrorCode::OptionExpired
+    );
+
+    require!(
+        ctx.accounts.data.amount_premium.is_none(),
+        ErrorCode::OptionAlreadyBought
+    );
+
+    require!(
+        amount_premium >= ctx.accounts.data.amount_premium_ask,
+        ErrorCode::PremiumTooLow
+    );
+    ctx.accounts.data.amount_premium = Some(amount_premium);
+    // Keep the mark for this expiry until the option settles
+    ctx.accounts.expiry.retain(
+        ctx.accounts.payer.key(),
+        ctx.accounts.data.feed_id,
+        ctx.bumps.expiry,
+    )?;
+    // Open options are assigned to whoever buys them first
+    ctx.accounts.data.buyer = Some(ctx.accounts.buyer.key());
+
+    // Protocol fee is taken out of the premium
+    let amount_fee = calc_fee(amount_premium, ctx.accounts.config.fee_bps_premium);
+    transfer_fee_to_treasury(
+        &ctx.accounts.token_program_premium,
+        &ctx.accounts.mint_premium,
+        ctx.accounts.ata_payer_premium.to_account_info(),
+        ctx.accounts.ata_treasury_premium.as_ref(),
+        ctx.accounts.payer.to_account_info(),
+        &[],
+        amount_fee,
+    )?;
+
+    // Transfer premium to seller or hold it in the vault until close
+    let ata_premium = if ctx.accounts.data.is_premium_to_seller {
+        ctx.accounts.ata_seller_premium.as_ref()
+    } else {
+        ctx.accounts.ata_vault_premium.as_ref()
+    }
+    .ok_or(ErrorCode::PremiumAccountMissing)?;
+
+    transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program_premium.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.ata_payer_premium.to_account_info(),
+                to: ata_premium.to_account_info(),
+                mint: ctx.accounts.mint_premium.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        ),
+        amount_premium - amount_fee,
+        ctx.accounts.mint_premium.decimals,
+    )?;
+
+    emit!(OptionBought {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Put,
+        seller: ctx.accounts.data.seller,
+        buyer: ctx.accounts.buyer.key(),
+        payer: ctx.accounts.payer.key(),
+        mint_premium: ctx.accounts.mint_premium.key(),
+        amount_premium,
+        amount_fee,
+        is_premium_to_seller: ctx.accounts.data.is_premium_to_seller,
+    });
+
+    Ok(())
+}
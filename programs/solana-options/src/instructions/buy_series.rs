@@ -0,0 +1,222 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::SeriesBought;
+use crate::math::calc_fee;
+use crate::mint::transfer_fee_to_treasury;
+use crate::state::{Config, OptionSeries, SeriesWriter};
+use crate::ExpiryData;
+
+#[derive(Accounts)]
+#[instruction(contracts: u64, amount_premium: u64)]
+pub struct BuySeries<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub buyer: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "option-series".as_bytes(),
+            series.mint_base.as_ref(),
+            series.mint_quote.as_ref(),
+            &series.amount_base.to_le_bytes(),
+            &series.amount_quote.to_le_bytes(),
+            &series.timestamp_expiry.to_le_bytes(),
+            series.feed_id.as_ref(),
+            &[series.exercise_style as u8],
+        ],
+        bump = series.bump,
+    )]
+    pub series: Account<'info, OptionSeries>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + ExpiryData::INIT_SPACE,
+        seeds = [
+          "expiry-meta".as_bytes(),
+          series.feed_id.as_ref(),
+          &series.timestamp_expiry.to_le_bytes(),
+        ],
+        bump,
+    )]
+    pub expiry: Account<'info, ExpiryData>,
+    #[account(
+        mut,
+        seeds = [
+            "series-writer".as_bytes(),
+            series.key().as_ref(),
+            writer.seller.as_ref(),
+        ],
+        bump = writer.bump,
+    )]
+    pub writer: Account<'info, SeriesWriter>,
+    #[account(constraint = mint_premium.key() == writer.mint_premium)]
+    pub mint_premium: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = ata_payer_premium.amount >= amount_premium,
+        associated_token::mint = mint_premium,
+        associated_token::authority = payer,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_payer_premium: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_premium,
+        associated_token::authority = writer.seller,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_seller_premium: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = mint_option.key() == series.mint_option)]
+    pub mint_option: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_option,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_buyer_option: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        associated_token::mint = mint_premium,
+        associated_token::authority = config,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_treasury_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_premium: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_buy_series(
+    ctx: Context<BuySeries>,
+    contracts: u64,
+    amount_premium: u64,
+) -> Result<()> {
+    require!(!ctx.accounts.config.is_buy_paused, ErrorCode::Paused);
+
+    let clock = Clock::get()?;
+
+    require!(
+        clock.unix_timestamp <= ctx.accounts.series.timestamp_expiry,
+        ErrorCode::OptionExpired
+    );
+
+    let contracts_sold = ctx
+        .accounts
+        .writer
+        .contracts_sold
+        .checked_add(contracts)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        contracts > 0 && contracts_sold <= ctx.accounts.writer.contracts_written,
+        ErrorCode::ContractsUnavailable
+    );
+
+    let amount_premium_ask = ctx
+        .accounts
+        .writer
+        .amount_premium_ask
+        .checked_mul(contracts)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        amount_premium >= amount_premium_ask,
+        ErrorCode::PremiumTooLow
+    );
+
+    // The series keeps the mark for this expiry until it settles
+    if ctx.accounts.series.contracts_sold == 0 {
+        ctx.accounts.expiry.retain(
+            ctx.accounts.payer.key(),
+            ctx.accounts.series.feed_id,
+            ctx.bumps.expiry,
+        )?;
+    }
+
+    ctx.accounts.writer.contracts_sold = contracts_sold;
+    ctx.accounts.series.contracts_sold = ctx
+        .accounts
+        .series
+        .contracts_sold
+        .checked_add(contracts)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Protocol fee is taken out of the premium
+    let amount_fee = calc_fee(amount_premium, ctx.accounts.config.fee_bps_premium);
+    transfer_fee_to_treasury(
+        &ctx.accounts.token_program_premium,
+        &ctx.accounts.mint_premium,
+        ctx.accounts.ata_payer_premium.to_account_info(),
+        ctx.accounts.ata_treasury_premium.as_ref(),
+        ctx.accounts.payer.to_account_info(),
+        &[],
+        amount_fee,
+    )?;
+
+    // Transfer premium to the writer
+    transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program_premium.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.ata_payer_premium.to_account_info(),
+                to: ctx.accounts.ata_seller_premium.to_account_info(),
+                mint: ctx.accounts.mint_premium.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        ),
+        amount_premium - amount_fee,
+        ctx.accounts.mint_premium.decimals,
+    )?;
+
+    // Mint the contracts to the buyer
+    let style = [ctx.accounts.series.exercise_style as u8];
+    let seeds = [
+        "option-series".as_bytes(),
+        ctx.accounts.series.mint_base.as_ref(),
+        ctx.accounts.series.mint_quote.as_ref(),
+        &ctx.accounts.series.amount_base.to_le_bytes(),
+        &ctx.accounts.series.amount_quote.to_le_bytes(),
+        &ctx.accounts.series.timestamp_expiry.to_le_bytes(),
+        ctx.accounts.series.feed_id.as_ref(),
+        &style,
+        &[ctx.accounts.series.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    mint_to(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.mint_option.to_account_info(),
+                to: ctx.accounts.ata_buyer_option.to_account_info(),
+                authority: ctx.accounts.series.to_account_info(),
+            },
+            signer,
+        ),
+        contracts,
+    )?;
+
+    emit!(SeriesBought {
+        series: ctx.accounts.series.key(),
+        seller: ctx.accounts.writer.seller,
+        buyer: ctx.accounts.buyer.key(),
+        payer: ctx.accounts.payer.key(),
+        contracts,
+        mint_premium: ctx.accounts.mint_premium.key(),
+        amount_premium,
+        amount_fee,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::OptionCancelled;
+use crate::mint::harvest_withheld_fees;
+use crate::state::{CoveredCall, OptionKind};
+use crate::MAX_CANCEL_REASON_LEN;
+
+#[derive(Accounts)]
+pub struct Cancel<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "covered-call".as_bytes(), 
+            seller.key().as_ref(),
+            mint_base.key().as_ref(),
+            &data.mint_quote.as_ref(),
+            &data.amount_base.to_le_bytes(),
+            &data.amount_quote.to_le_bytes(),
+            &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = data.bump,
+        close = seller,
+    )]
+    pub data: Account<'info, CoveredCall>,
+    #[account(mut, constraint = mint_base.key() == data.mint_base)]
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_base,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_seller_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_base,
+        associated_token::authority = data,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_base: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_cancel(ctx: Context<Cancel>, reason: String) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(
+        ctx.accounts.data.amount_premium.is_none(),
+        ErrorCode::OptionAlreadyBought
+    );
+    require!(
+        reason.len() <= MAX_CANCEL_REASON_LEN,
+        ErrorCode::CancelReasonTooLong
+    );
+
+    let seeds = [
+        "covered-call".as_bytes(),
+        ctx.accounts.data.seller.as_ref(),
+        ctx.accounts.data.mint_base.as_ref(),
+        ctx.accounts.data.mint_quote.as_ref(),
+        &ctx.accounts.data.amount_base.to_le_bytes(),
+        &ctx.accounts.data.amount_quote.to_le_bytes(),
+        &ctx.accounts.data.timestamp_expiry.to_le_bytes(),
+        &[ctx.accounts.data.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let amount_collateral = ctx.accounts.ata_vault_base.amount;
+
+    // Return base to seller
+    if amount_collateral > 0 {
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.ata_vault_base.to_account_info(),
+                    to: ctx.accounts.ata_seller_base.to_account_info(),
+                    mint: ctx.accounts.mint_base.to_account_info(),
+                    authority: ctx.accounts.data.to_account_info(),
+                },
+                signer,
+            ),
+            amount_collateral,
+            ctx.accounts.mint_base.decimals,
+        )?;
+    }
+
+    harvest_withheld_fees(
+        &ctx.accounts.token_program,
+        &ctx.accounts.mint_base,
+        ctx.accounts.ata_vault_base.to_account_info(),
+    )?;
+
+    close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: ctx.accounts.ata_vault_base.to_account_info(),
+            destination: ctx.accounts.seller.to_account_info(),
+            authority: ctx.accounts.data.to_account_info(),
+        },
+        signer,
+    ))?;
+
+    emit!(OptionCancelled {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Call,
+        seller: ctx.accounts.data.seller,
+        amount_collateral,
+        reason,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
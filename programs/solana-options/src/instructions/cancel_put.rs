@@ -0,0 +1,126 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::error::ErrorCode;
+use crate::events::OptionCancelled;
+use crate::mint::harvest_withheld_fees;
+use crate::state::{CashSecuredPut, OptionKind};
+use crate::MAX_CANCEL_REASON_LEN;
+
+#[derive(Accounts)]
+pub struct CancelPut<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "cash-secured-put".as_bytes(),
+            seller.key().as_ref(),
+            &data.mint_base.as_ref(),
+            mint_quote.key().as_ref(),
+            &data.amount_base.to_le_bytes(),
+            &data.amount_quote.to_le_bytes(),
+            &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = data.bump,
+        close = seller,
+    )]
+    pub data: Account<'info, CashSecuredPut>,
+    #[account(mut, constraint = mint_quote.key() == data.mint_quote)]
+    pub mint_quote: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_quote,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_seller_quote: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_quote,
+        associated_token::authority = data,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_quote: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_cancel_put(ctx: Context<CancelPut>, reason: String) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(
+        ctx.accounts.data.amount_premium.is_none(),
+        ErrorCode::OptionAlreadyBought
+    );
+    require!(
+        reason.len() <= MAX_CANCEL_REASON_LEN,
+        ErrorCode::CancelReasonTooLong
+    );
+
+    let seeds = [
+        "cash-secured-put".as_bytes(),
+        ctx.accounts.data.seller.as_ref(),
+        ctx.accounts.data.mint_base.as_ref(),
+        ctx.accounts.data.mint_quote.as_ref(),
+        &ctx.accounts.data.amount_base.to_le_bytes(),
+        &ctx.accounts.data.amount_quote.to_le_bytes(),
+        &ctx.accounts.data.timestamp_expiry.to_le_bytes(),
+        &[ctx.accounts.data.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let amount_collateral = ctx.accounts.ata_vault_quote.amount;
+
+    // Return quote to seller
+    if amount_collateral > 0 {
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.ata_vault_quote.to_account_info(),
+                    to: ctx.accounts.ata_seller_quote.to_account_info(),
+                    mint: ctx.accounts.mint_quote.to_account_info(),
+                    authority: ctx.accounts.data.to_account_info(),
+                },
+                signer,
+            ),
+            amount_collateral,
+            ctx.accounts.mint_quote.decimals,
+        )?;
+    }
+
+    harvest_withheld_fees(
+        &ctx.accounts.token_program,
+        &ctx.accounts.mint_quote,
+        ctx.accounts.ata_vault_quote.to_account_info(),
+    )?;
+
+    close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: ctx.accounts.ata_vault_quote.to_account_info(),
+            destination: ctx.accounts.seller.to_account_info(),
+            authority: ctx.accounts.data.to_account_info(),
+        },
+        signer,
+    ))?;
+
+    emit!(OptionCancelled {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Put,
+        seller: ctx.accounts.data.seller,
+        amount_collateral,
+        reason,
+        timestamp: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,265 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::events::OptionClosed;
+use crate::math::calc_strike;
+use crate::mint::harvest_withheld_fees;
+use crate::state::{CashSecuredPut, Config, OptionKind};
+use crate::{error::ErrorCode, ExpiryData};
+
+#[derive(Accounts)]
+pub struct ClosePut<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, constraint = seller.key() == data.seller)]
+    pub seller: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "cash-secured-put".as_bytes(),
+            seller.key().as_ref(),
+            &data.mint_base.as_ref(),
+            mint_quote.key().as_ref(),
+            &data.amount_base.to_le_bytes(),
+            &data.amount_quote.to_le_bytes(),
+            &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = data.bump,
+        close = seller,
+    )]
+    pub data: Account<'info, CashSecuredPut>,
+    #[account(
+        mut,
+        seeds = [
+          "expiry-meta".as_bytes(),
+          data.feed_id.as_ref(),
+          &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = expiry.bump,
+        constraint = expiry.feed_id == data.feed_id,
+    )]
+    pub expiry: Option<Account<'info, ExpiryData>>,
+    #[account(mut, constraint = mint_quote.key() == data.mint_quote)]
+    pub mint_quote: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = data.mint_quote,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_seller_quote: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_quote,
+        associated_token::authority = data,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_quote: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = mint_premium.key() == data.mint_premium)]
+    pub mint_premium: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = data.mint_premium,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_seller_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = data.mint_premium,
+        associated_token::authority = data,
+        associated_token::token_program = token_program_premium,
+    )]
+    pub ata_vault_premium: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_premium: Option<Interface<'info, TokenInterface>>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_close_put(ctx: Context<ClosePut>) -> Result<()> {
+    let clock = Clock::get()?;
+
+    let seeds = [
+        "cash-secured-put".as_bytes(),
+        ctx.accounts.data.seller.as_ref(),
+        ctx.accounts.data.mint_base.as_ref(),
+        ctx.accounts.data.mint_quote.as_ref(),
+        &ctx.accounts.data.amount_base.to_le_bytes(),
+        &ctx.accounts.data.amount_quote.to_le_bytes(),
+        &ctx.accounts.data.timestamp_expiry.to_le_bytes(),
+        &[ctx.accounts.data.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    let is_expired = clock.unix_timestamp >= ctx.accounts.data.timestamp_expiry;
+    let is_exercised = ctx.accounts.data.is_exercised;
+    // Bought options need the expiry to tell an unmarked option from one left out
+    require!(
+        is_exercised || ctx.accounts.data.amount_premium.is_none() || ctx.accounts.expiry.is_some(),
+        ErrorCode::OptionNotMarked
+    );
+    // Only a price the option would settle at counts
+    let settlement = ctx.accounts.expiry.as_ref().and_then(|x| {
+        x.settlement_price(
+            ctx.accounts.data.timestamp_expiry,
+            ctx.accounts.data.mark_window,
+            ctx.accounts.data.max_conf_bps,
+            ctx.accounts.data.price_source,
+            clock.unix_timestamp,
+        )
+        .ok()
+        .map(|price| (price, x.exponent))
+    });
+    // Strike is computed in the exponent the oracle reported the mark in
+    let is_otm = match settlement {
+        Some((price, exponent)) => {
+            let strike = calc_strike(
+                ctx.accounts.data.amount_base,
+                ctx.accounts.data.amount_quote,
+                ctx.accounts.data.decimals_base,
+                ctx.accounts.data.decimals_quote,
+                exponent,
+            )?;
+            price > 0 && price >= strike
+        }
+        None => false,
+    };
+    // A bought option without a price to settle at lapses to the seller
+    let is_lapsed = !is_exercised
+        && ctx.accounts.data.amount_premium.is_some()
+        && settlement.is_none()
+        && ExpiryData::can_lapse(
+            ctx.accounts.data.timestamp_expiry,
+            clock.unix_timestamp,
+            &ctx.accounts.config,
+        );
+
+    require!(
+        (is_expired && (is_exercised || is_otm))
+            || is_lapsed
+            || ctx.accounts.data.amount_premium.is_none(),
+        ErrorCode::OptionCannotBeClosedYet,
+    );
+
+    // Exercised options released the mark already, unbought ones never held it
+    if !is_exercised && ctx.accounts.data.amount_premium.is_some() {
+        ctx.accounts
+            .expiry
+            .as_mut()
+            .ok_or(ErrorCode::ExpiryAccountMissing)?
+            .release();
+    }
+
+    let amount_collateral = ctx.accounts.ata_vault_quote.amount;
+    let mut amount_premium = 0;
+
+    // Transfer quote to seller
+    if ctx.accounts.ata_vault_quote.amount > 0 {
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.ata_vault_quote.to_account_info(),
+                    to: ctx.accounts.ata_seller_quote.to_account_info(),
+                    mint: ctx.accounts.mint_quote.to_account_info(),
+                    authority: ctx.accounts.data.to_account_info(),
+                },
+                signer,
+            ),
+            ctx.accounts.ata_vault_quote.amount,
+            ctx.accounts.mint_quote.decimals,
+        )?;
+    }
+
+    // Transfer premium to seller if it is not held in the quote vault
+    if ctx.accounts.data.amount_premium.is_some()
+        && !ctx.accounts.data.is_premium_to_seller
+        && ctx.accounts.data.mint_premium != ctx.accounts.data.mint_quote
+    {
+        let (
+            Some(mint_premium),
+            Some(ata_seller_premium),
+            Some(ata_vault_premium),
+            Some(token_program_premium),
+        ) = (
+            ctx.accounts.mint_premium.as_ref(),
+            ctx.accounts.ata_seller_premium.as_ref(),
+            ctx.accounts.ata_vault_premium.as_ref(),
+            ctx.accounts.token_program_premium.as_ref(),
+        )
+        else {
+            return err!(ErrorCode::PremiumAccountMissing);
+        };
+        amount_premium = ata_vault_premium.amount;
+
+        if ata_vault_premium.amount > 0 {
+            transfer_checked(
+                CpiContext::new_with_signer(
+                    token_program_premium.to_account_info(),
+                    TransferChecked {
+                        from: ata_vault_premium.to_account_info(),
+                        to: ata_seller_premium.to_account_info(),
+                        mint: mint_premium.to_account_info(),
+                        authority: ctx.accounts.data.to_account_info(),
+                    },
+                    signer,
+                ),
+                ata_vault_premium.amount,
+                mint_premium.decimals,
+            )?;
+        }
+
+        harvest_withheld_fees(
+            token_program_premium,
+            mint_premium,
+            ata_vault_premium.to_account_info(),
+        )?;
+
+        close_account(CpiContext::new_with_signer(
+            token_program_premium.to_account_info(),
+            CloseAccount {
+                account: ata_vault_premium.to_account_info(),
+                destination: ctx.accounts.seller.to_account_info(),
+                authority: ctx.accounts.data.to_account_info(),
+            },
+            signer,
+        ))?;
+    }
+
+    harvest_withheld_fees(
+        &ctx.accounts.token_program,
+        &ctx.accounts.mint_quote,
+        ctx.accounts.ata_vault_quote.to_account_info(),
+    )?;
+
+    close_account(CpiContext::new_with_signer(
+        ctx.accounts.token_program.to_account_info(),
+        CloseAccount {
+            account: ctx.accounts.ata_vault_quote.to_account_info(),
+            destination: ctx.accounts.seller.to_account_info(),
+            authority: ctx.accounts.data.to_account_info(),
+        },
+        signer,
+    ))?;
+
+    emit!(OptionClosed {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Put,
+        seller: ctx.accounts.data.seller,
+        amount_collateral,
+        amount_premium,
+        amount_quote: 0,
+        is_lapsed,
+    });
+
+    Ok(())
+}
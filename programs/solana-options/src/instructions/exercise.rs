@@ -1,23 +1,32 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{transfer_checked, Mint, Token, TokenAccount, TransferChecked},
+    token_interface::{
+        burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
 
-use crate::math::{calc_strike, get_settlements};
-use crate::state::CoveredCall;
+use crate::events::OptionExercised;
+use crate::math::{calc_conf_bps, calc_fee, calc_strike, get_contracts_amount, get_settlements};
+use crate::mint::{get_transfer_fee, get_transfer_inverse_fee, transfer_fee_to_treasury};
+use crate::state::{Config, CoveredCall, ExerciseStyle, OptionKind, SettlementType};
 use crate::{error::ErrorCode, ExpiryData};
+use crate::{EXERCISE_PRICE_MAX_AGE, PRICE_EXPONENT};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 #[derive(Accounts)]
 pub struct Exercise<'info> {
-    #[account(mut, constraint = buyer.key() == data.buyer)]
+    // Tokenized options pay whoever burns the option token
+    #[account(
+        mut,
+        constraint = data.mint_option.is_some() || data.buyer == Some(buyer.key()),
+    )]
     pub buyer: Signer<'info>,
     #[account(
         mut,
         seeds = [
             "covered-call".as_bytes(), 
             data.seller.as_ref(),
-            buyer.key().as_ref(),
             mint_base.key().as_ref(),
             mint_quote.key().as_ref(),
             &data.amount_base.to_le_bytes(),
@@ -28,39 +37,82 @@ pub struct Exercise<'info> {
     )]
     pub data: Account<'info, CoveredCall>,
     #[account(
+        mut,
         seeds = [
           "expiry-meta".as_bytes(),
+          data.feed_id.as_ref(),
           &data.timestamp_expiry.to_le_bytes(),
         ],
         bump = expiry.bump,
+        constraint = expiry.feed_id == data.feed_id,
     )]
-    pub expiry: Account<'info, ExpiryData>,
+    pub expiry: Option<Account<'info, ExpiryData>>,
+    // Prices an american exercise before expiry
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
     #[account( constraint = mint_base.key() == data.mint_base)]
-    pub mint_base: Account<'info, Mint>,
+    pub mint_base: InterfaceAccount<'info, Mint>,
     #[account( constraint = mint_quote.key() == data.mint_quote)]
-    pub mint_quote: Account<'info, Mint>,
+    pub mint_quote: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         associated_token::mint = data.mint_base,
         associated_token::authority = buyer,
+        associated_token::token_program = token_program,
     )]
-    pub ata_buyer_base: Account<'info, TokenAccount>,
+    pub ata_buyer_base: InterfaceAccount<'info, TokenAccount>,
     #[account(
         mut,
         associated_token::mint = mint_base,
         associated_token::authority = data,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = data.mint_option == Some(mint_option.key()))]
+    pub mint_option: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_option,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program,
     )]
-    pub ata_vault_base: Account<'info, TokenAccount>,
+    pub ata_buyer_option: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_quote,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program_quote,
+    )]
+    pub ata_buyer_quote: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_quote,
+        associated_token::authority = data,
+        associated_token::token_program = token_program_quote,
+    )]
+    pub ata_vault_quote: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_base,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_treasury_base: Option<InterfaceAccount<'info, TokenAccount>>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub token_program_quote: Option<Interface<'info, TokenInterface>>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn handle_exercise(ctx: Context<Exercise>) -> Result<()> {
+pub fn handle_exercise(ctx: Context<Exercise>, quantity: u64) -> Result<()> {
     let clock = Clock::get()?;
 
+    let is_expired = clock.unix_timestamp >= ctx.accounts.data.timestamp_expiry;
     require!(
-        clock.unix_timestamp >= ctx.accounts.data.timestamp_expiry,
+        is_expired || ctx.accounts.data.exercise_style == ExerciseStyle::American,
         ErrorCode::OptionNotExpired
     );
 
@@ -70,27 +122,83 @@ pub fn handle_exercise(ctx: Context<Exercise>) -> Result<()> {
     );
 
     require!(
-        ctx.accounts.data.is_exercised == false,
+        !ctx.accounts.data.is_exercised,
         ErrorCode::OptionAlreadyExercised
     );
-    // Incase expiry account was initialized but not set
-    require!(ctx.accounts.expiry.price != 0, ErrorCode::OptionNotMarked);
 
+    let contracts_exercised = ctx
+        .accounts
+        .data
+        .contracts_exercised
+        .checked_add(quantity)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require!(
+        quantity > 0 && contracts_exercised <= ctx.accounts.data.contracts,
+        ErrorCode::InvalidQuantity
+    );
+
+    // Physical settlement swaps at the strike whatever the price, so only cash settlement
+    // is priced, against the mark after expiry or a fresh price when exercising early
+    let (price, exponent) = if ctx.accounts.data.settlement == SettlementType::Physical {
+        (0, PRICE_EXPONENT)
+    } else if is_expired {
+        let Some(expiry) = ctx.accounts.expiry.as_ref() else {
+            return err!(ErrorCode::OptionNotMarked);
+        };
+        let price = expiry.settlement_price(
+            ctx.accounts.data.timestamp_expiry,
+            ctx.accounts.data.mark_window,
+            ctx.accounts.data.max_conf_bps,
+            ctx.accounts.data.price_source,
+            clock.unix_timestamp,
+        )?;
+        (price, expiry.exponent)
+    } else {
+        let Some(price_update) = ctx.accounts.price_update.as_ref() else {
+            return err!(ErrorCode::PriceUpdateMissing);
+        };
+        let price = price_update.get_price_no_older_than(
+            &clock,
+            EXERCISE_PRICE_MAX_AGE,
+            &ctx.accounts.data.feed_id,
+        )?;
+        require!(
+            calc_conf_bps(price.conf, price.price) <= u64::from(ctx.accounts.data.max_conf_bps),
+            ErrorCode::ConfidenceTooWide
+        );
+        (price.price, price.exponent)
+    };
+
+    // Strike is computed in the exponent the oracle reported the price in, if any
     let strike = calc_strike(
         ctx.accounts.data.amount_base,
         ctx.accounts.data.amount_quote,
-    );
+        ctx.accounts.data.decimals_base,
+        ctx.accounts.data.decimals_quote,
+        exponent,
+    )?;
 
-    let [_, amount] = get_settlements(
-        strike,
-        ctx.accounts.expiry.price.try_into().unwrap(),
+    // Settle only the exercised share, the remainder stays live
+    let amount_base = get_contracts_amount(
         ctx.accounts.data.amount_base,
-    );
+        quantity,
+        ctx.accounts.data.contracts,
+    )?;
+    let ([amount_seller, amount], amount_quote) = match ctx.accounts.data.settlement {
+        SettlementType::Cash => (get_settlements(strike, price, amount_base), 0),
+        SettlementType::Physical => (
+            [0, amount_base],
+            get_contracts_amount(
+                ctx.accounts.data.amount_quote,
+                quantity,
+                ctx.accounts.data.contracts,
+            )?,
+        ),
+    };
 
     let seeds = [
         "covered-call".as_bytes(),
         ctx.accounts.data.seller.as_ref(),
-        ctx.accounts.data.buyer.as_ref(),
         ctx.accounts.data.mint_base.as_ref(),
         ctx.accounts.data.mint_quote.as_ref(),
         &ctx.accounts.data.amount_base.to_le_bytes(),
@@ -100,6 +208,68 @@ pub fn handle_exercise(ctx: Context<Exercise>) -> Result<()> {
     ];
     let signer = &[&seeds[..]];
 
+    // Burn the option token of the holder
+    if ctx.accounts.data.mint_option.is_some() {
+        let (Some(mint_option), Some(ata_buyer_option)) = (
+            ctx.accounts.mint_option.as_ref(),
+            ctx.accounts.ata_buyer_option.as_ref(),
+        ) else {
+            return err!(ErrorCode::OptionTokenMissing);
+        };
+
+        burn(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Burn {
+                    mint: mint_option.to_account_info(),
+                    from: ata_buyer_option.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            ),
+            quantity,
+        )?;
+    }
+
+    // Transfer quote from buyer to vault, grossed up so the seller can collect the full amount
+    if ctx.accounts.data.settlement == SettlementType::Physical {
+        let (Some(ata_buyer_quote), Some(ata_vault_quote), Some(token_program_quote)) = (
+            ctx.accounts.ata_buyer_quote.as_ref(),
+            ctx.accounts.ata_vault_quote.as_ref(),
+            ctx.accounts.token_program_quote.as_ref(),
+        ) else {
+            return err!(ErrorCode::QuoteAccountMissing);
+        };
+        let fee = get_transfer_inverse_fee(&ctx.accounts.mint_quote, amount_quote)?;
+
+        transfer_checked(
+            CpiContext::new(
+                token_program_quote.to_account_info(),
+                TransferChecked {
+                    from: ata_buyer_quote.to_account_info(),
+                    to: ata_vault_quote.to_account_info(),
+                    mint: ctx.accounts.mint_quote.to_account_info(),
+                    authority: ctx.accounts.buyer.to_account_info(),
+                },
+            ),
+            amount_quote
+                .checked_add(fee)
+                .ok_or(ErrorCode::MathOverflow)?,
+            ctx.accounts.mint_quote.decimals,
+        )?;
+    }
+
+    // Protocol fee is taken out of what the buyer is paid
+    let amount_fee = calc_fee(amount, ctx.accounts.config.fee_bps_settlement);
+    transfer_fee_to_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.mint_base,
+        ctx.accounts.ata_vault_base.to_account_info(),
+        ctx.accounts.ata_treasury_base.as_ref(),
+        ctx.accounts.data.to_account_info(),
+        signer,
+        amount_fee,
+    )?;
+
     // Transfer base from vault to buyer
     transfer_checked(
         CpiContext::new_with_signer(
@@ -112,11 +282,37 @@ pub fn handle_exercise(ctx: Context<Exercise>) -> Result<()> {
             },
             signer,
         ),
-        amount,
+        amount - amount_fee,
         ctx.accounts.mint_base.decimals,
     )?;
 
-    ctx.accounts.data.is_exercised = true;
+    ctx.accounts.data.contracts_exercised = contracts_exercised;
+    ctx.accounts.data.is_exercised = contracts_exercised == ctx.accounts.data.contracts;
+    if ctx.accounts.data.is_exercised {
+        ctx.accounts
+            .expiry
+            .as_mut()
+            .ok_or(ErrorCode::ExpiryAccountMissing)?
+            .release();
+    }
+
+    // Report what the buyer actually receives after transfer fees
+    let fee = get_transfer_fee(&ctx.accounts.mint_base, amount - amount_fee)?;
+
+    emit!(OptionExercised {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Call,
+        seller: ctx.accounts.data.seller,
+        buyer: ctx.accounts.buyer.key(),
+        mint: ctx.accounts.mint_base.key(),
+        strike,
+        price,
+        contracts: quantity,
+        amount_seller,
+        amount_buyer: amount - amount_fee - fee,
+        amount_fee,
+        amount_quote_paid: amount_quote,
+    });
 
     Ok(())
 }
@@ -0,0 +1,174 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::events::OptionExercised;
+use crate::math::{calc_fee, calc_strike, get_put_settlements};
+use crate::mint::{get_transfer_fee, transfer_fee_to_treasury};
+use crate::state::{CashSecuredPut, Config, OptionKind};
+use crate::{error::ErrorCode, ExpiryData};
+
+#[derive(Accounts)]
+pub struct ExercisePut<'info> {
+    #[account(mut, constraint = data.buyer == Some(buyer.key()))]
+    pub buyer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "cash-secured-put".as_bytes(),
+            data.seller.as_ref(),
+            mint_base.key().as_ref(),
+            mint_quote.key().as_ref(),
+            &data.amount_base.to_le_bytes(),
+            &data.amount_quote.to_le_bytes(),
+            &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = data.bump,
+    )]
+    pub data: Account<'info, CashSecuredPut>,
+    #[account(
+        mut,
+        seeds = [
+          "expiry-meta".as_bytes(),
+          data.feed_id.as_ref(),
+          &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = expiry.bump,
+        constraint = expiry.feed_id == data.feed_id,
+    )]
+    pub expiry: Account<'info, ExpiryData>,
+    #[account( constraint = mint_base.key() == data.mint_base)]
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    #[account( constraint = mint_quote.key() == data.mint_quote)]
+    pub mint_quote: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = data.mint_quote,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_buyer_quote: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_quote,
+        associated_token::authority = data,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_quote: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_quote,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_treasury_quote: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_exercise_put(ctx: Context<ExercisePut>) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(
+        clock.unix_timestamp >= ctx.accounts.data.timestamp_expiry,
+        ErrorCode::OptionNotExpired
+    );
+
+    require!(
+        ctx.accounts.data.amount_premium.is_some(),
+        ErrorCode::OptionNotPurchased
+    );
+
+    require!(
+        !ctx.accounts.data.is_exercised,
+        ErrorCode::OptionAlreadyExercised
+    );
+    let price = ctx.accounts.expiry.settlement_price(
+        ctx.accounts.data.timestamp_expiry,
+        ctx.accounts.data.mark_window,
+        ctx.accounts.data.max_conf_bps,
+        ctx.accounts.data.price_source,
+        clock.unix_timestamp,
+    )?;
+
+    // Strike is computed in the exponent the oracle reported the mark in
+    let strike = calc_strike(
+        ctx.accounts.data.amount_base,
+        ctx.accounts.data.amount_quote,
+        ctx.accounts.data.decimals_base,
+        ctx.accounts.data.decimals_quote,
+        ctx.accounts.expiry.exponent,
+    )?;
+
+    let [amount_seller, amount] =
+        get_put_settlements(strike, price, ctx.accounts.data.amount_quote);
+
+    let seeds = [
+        "cash-secured-put".as_bytes(),
+        ctx.accounts.data.seller.as_ref(),
+        ctx.accounts.data.mint_base.as_ref(),
+        ctx.accounts.data.mint_quote.as_ref(),
+        &ctx.accounts.data.amount_base.to_le_bytes(),
+        &ctx.accounts.data.amount_quote.to_le_bytes(),
+        &ctx.accounts.data.timestamp_expiry.to_le_bytes(),
+        &[ctx.accounts.data.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Protocol fee is taken out of what the buyer is paid
+    let amount_fee = calc_fee(amount, ctx.accounts.config.fee_bps_settlement);
+    transfer_fee_to_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.mint_quote,
+        ctx.accounts.ata_vault_quote.to_account_info(),
+        ctx.accounts.ata_treasury_quote.as_ref(),
+        ctx.accounts.data.to_account_info(),
+        signer,
+        amount_fee,
+    )?;
+
+    // Transfer quote from vault to buyer
+    transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.ata_vault_quote.to_account_info(),
+                to: ctx.accounts.ata_buyer_quote.to_account_info(),
+                mint: ctx.accounts.mint_quote.to_account_info(),
+                authority: ctx.accounts.data.to_account_info(),
+            },
+            signer,
+        ),
+        amount - amount_fee,
+        ctx.accounts.mint_quote.decimals,
+    )?;
+
+    ctx.accounts.data.is_exercised = true;
+    ctx.accounts.expiry.release();
+
+    // Report what the buyer actually receives after transfer fees
+    let fee = get_transfer_fee(&ctx.accounts.mint_quote, amount - amount_fee)?;
+
+    emit!(OptionExercised {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Put,
+        seller: ctx.accounts.data.seller,
+        buyer: ctx.accounts.buyer.key(),
+        mint: ctx.accounts.mint_quote.key(),
+        strike,
+        price,
+        contracts: 1,
+        amount_seller,
+        amount_buyer: amount - amount_fee - fee,
+        amount_fee,
+        amount_quote_paid: 0,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,234 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::events::SeriesExercised;
+use crate::math::{calc_conf_bps, calc_fee, calc_strike, get_settlements};
+use crate::mint::{get_transfer_fee, transfer_fee_to_treasury};
+use crate::state::{Config, ExerciseStyle, OptionSeries, PriceSource};
+use crate::EXERCISE_PRICE_MAX_AGE;
+use crate::{error::ErrorCode, ExpiryData};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
+
+#[derive(Accounts)]
+pub struct ExerciseSeries<'info> {
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "option-series".as_bytes(),
+            series.mint_base.as_ref(),
+            series.mint_quote.as_ref(),
+            &series.amount_base.to_le_bytes(),
+            &series.amount_quote.to_le_bytes(),
+            &series.timestamp_expiry.to_le_bytes(),
+            series.feed_id.as_ref(),
+            &[series.exercise_style as u8],
+        ],
+        bump = series.bump,
+    )]
+    pub series: Account<'info, OptionSeries>,
+    #[account(
+        mut,
+        seeds = [
+          "expiry-meta".as_bytes(),
+          series.feed_id.as_ref(),
+          &series.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = expiry.bump,
+        constraint = expiry.feed_id == series.feed_id,
+    )]
+    pub expiry: Option<Account<'info, ExpiryData>>,
+    // Prices an american exercise before expiry
+    pub price_update: Option<Account<'info, PriceUpdateV2>>,
+    #[account(constraint = mint_base.key() == series.mint_base)]
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_base,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_buyer_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_base,
+        associated_token::authority = series,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut, constraint = mint_option.key() == series.mint_option)]
+    pub mint_option: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_option,
+        associated_token::authority = buyer,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_buyer_option: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(
+        init_if_needed,
+        payer = buyer,
+        associated_token::mint = mint_base,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_treasury_base: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_exercise_series(ctx: Context<ExerciseSeries>, quantity: u64) -> Result<()> {
+    let clock = Clock::get()?;
+
+    let is_expired = clock.unix_timestamp >= ctx.accounts.series.timestamp_expiry;
+    require!(
+        is_expired || ctx.accounts.series.exercise_style == ExerciseStyle::American,
+        ErrorCode::OptionNotExpired
+    );
+    require!(quantity > 0, ErrorCode::InvalidQuantity);
+    require!(!ctx.accounts.series.is_lapsed, ErrorCode::OptionLapsed);
+
+    // Settle against the mark after expiry, or a fresh price when exercising early
+    let (price, exponent) = if is_expired {
+        if ctx.accounts.series.price_settled == 0 {
+            let Some(expiry) = ctx.accounts.expiry.as_mut() else {
+                return err!(ErrorCode::OptionNotMarked);
+            };
+            let price = expiry.settlement_price(
+                ctx.accounts.series.timestamp_expiry,
+                ctx.accounts.series.mark_window,
+                ctx.accounts.series.max_conf_bps,
+                PriceSource::Mark,
+                clock.unix_timestamp,
+            )?;
+            // Keep the mark on the series so the expiry can be closed
+            ctx.accounts.series.price_settled = price;
+            ctx.accounts.series.exponent_settled = expiry.exponent;
+            expiry.release();
+        }
+        (
+            ctx.accounts.series.price_settled,
+            ctx.accounts.series.exponent_settled,
+        )
+    } else {
+        let Some(price_update) = ctx.accounts.price_update.as_ref() else {
+            return err!(ErrorCode::PriceUpdateMissing);
+        };
+        let price = price_update.get_price_no_older_than(
+            &clock,
+            EXERCISE_PRICE_MAX_AGE,
+            &ctx.accounts.series.feed_id,
+        )?;
+        require!(
+            calc_conf_bps(price.conf, price.price) <= u64::from(ctx.accounts.series.max_conf_bps),
+            ErrorCode::ConfidenceTooWide
+        );
+        (price.price, price.exponent)
+    };
+
+    // Strike is computed in the exponent the oracle reported the price in
+    let strike = calc_strike(
+        ctx.accounts.series.amount_base,
+        ctx.accounts.series.amount_quote,
+        ctx.accounts.series.decimals_base,
+        ctx.accounts.series.decimals_quote,
+        exponent,
+    )?;
+    let amount_base = ctx
+        .accounts
+        .series
+        .amount_base
+        .checked_mul(quantity)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let [_, amount] = get_settlements(strike, price, amount_base);
+
+    // Burn the contracts of the holder, which fails if they hold fewer
+    burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.mint_option.to_account_info(),
+                from: ctx.accounts.ata_buyer_option.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        ),
+        quantity,
+    )?;
+
+    let style = [ctx.accounts.series.exercise_style as u8];
+    let seeds = [
+        "option-series".as_bytes(),
+        ctx.accounts.series.mint_base.as_ref(),
+        ctx.accounts.series.mint_quote.as_ref(),
+        &ctx.accounts.series.amount_base.to_le_bytes(),
+        &ctx.accounts.series.amount_quote.to_le_bytes(),
+        &ctx.accounts.series.timestamp_expiry.to_le_bytes(),
+        ctx.accounts.series.feed_id.as_ref(),
+        &style,
+        &[ctx.accounts.series.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    // Protocol fee is taken out of what the buyer is paid
+    let amount_fee = calc_fee(amount, ctx.accounts.config.fee_bps_settlement);
+    transfer_fee_to_treasury(
+        &ctx.accounts.token_program,
+        &ctx.accounts.mint_base,
+        ctx.accounts.ata_vault_base.to_account_info(),
+        ctx.accounts.ata_treasury_base.as_ref(),
+        ctx.accounts.series.to_account_info(),
+        signer,
+        amount_fee,
+    )?;
+
+    // Transfer base from the pooled vault to buyer
+    transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.ata_vault_base.to_account_info(),
+                to: ctx.accounts.ata_buyer_base.to_account_info(),
+                mint: ctx.accounts.mint_base.to_account_info(),
+                authority: ctx.accounts.series.to_account_info(),
+            },
+            signer,
+        ),
+        amount - amount_fee,
+        ctx.accounts.mint_base.decimals,
+    )?;
+
+    let series = &mut ctx.accounts.series;
+    series.contracts_exercised = series
+        .contracts_exercised
+        .checked_add(quantity)
+        .ok_or(ErrorCode::MathOverflow)?;
+    series.amount_base_paid = series
+        .amount_base_paid
+        .checked_add(amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Report what the buyer actually receives after transfer fees
+    let fee = get_transfer_fee(&ctx.accounts.mint_base, amount - amount_fee)?;
+
+    emit!(SeriesExercised {
+        series: ctx.accounts.series.key(),
+        buyer: ctx.accounts.buyer.key(),
+        contracts: quantity,
+        strike,
+        price,
+        amount_buyer: amount - amount_fee - fee,
+        amount_fee,
+    });
+
+    Ok(())
+}
@@ -1,18 +1,22 @@
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{transfer_checked, Mint, Token, TokenAccount, TransferChecked},
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 
+use crate::constants::{MAX_CONF_BPS, MAX_MARK_WINDOW, PRICE_EXPONENT};
 use crate::error::ErrorCode;
-use crate::state::CoveredCall;
+use crate::events::OptionCreated;
+use crate::math::calc_strike;
+use crate::mint::{get_transfer_inverse_fee, validate_mint};
+use crate::state::{Config, CoveredCall, ExerciseStyle, OptionKind, PriceSource, SettlementType};
 
 #[derive(Accounts)]
-#[instruction(amount_base: u64, amount_quote: u64, timestamp_expiry: i64)]
+#[instruction(amount_base: u64, amount_quote: u64, timestamp_expiry: i64, feed_id: [u8; 32], amount_premium_ask: u64, is_premium_to_seller: bool, settlement: SettlementType, exercise_style: ExerciseStyle, contracts: u64, max_conf_bps: u16, mark_window: i64, price_source: PriceSource)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub seller: Signer<'info>,
-    pub buyer: SystemAccount<'info>,
+    pub buyer: Option<SystemAccount<'info>>,
     #[account(
         init,
         payer = seller,
@@ -20,7 +24,6 @@ pub struct Initialize<'info> {
         seeds = [
             b"covered-call",
             seller.key().as_ref(),
-            buyer.key().as_ref(),
             mint_base.key().as_ref(),
             mint_quote.key().as_ref(),
             amount_base.to_le_bytes().as_ref(),
@@ -30,33 +33,58 @@ pub struct Initialize<'info> {
         bump,
     )]
     pub data: Account<'info, CoveredCall>,
-    pub mint_base: Account<'info, Mint>,
-    pub mint_quote: Account<'info, Mint>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    pub mint_quote: InterfaceAccount<'info, Mint>,
+    pub mint_premium: InterfaceAccount<'info, Mint>,
     #[account(
         mut,
         constraint = ata_seller_base.amount >= amount_base,
         associated_token::mint = mint_base,
         associated_token::authority = seller,
+        associated_token::token_program = token_program,
     )]
-    pub ata_seller_base: Account<'info, TokenAccount>,
+    pub ata_seller_base: InterfaceAccount<'info, TokenAccount>,
     #[account(
         init,
         payer = seller,
         associated_token::mint = mint_base,
         associated_token::authority = data,
+        associated_token::token_program = token_program,
     )]
-    pub ata_vault_base: Account<'info, TokenAccount>,
+    pub ata_vault_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        mint::decimals = 0,
+        mint::authority = data,
+        mint::token_program = token_program,
+    )]
+    pub mint_option: Option<InterfaceAccount<'info, Mint>>,
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn handle_initialize(
     ctx: Context<Initialize>,
     amount_base: u64,
     amount_quote: u64,
     timestamp_expiry: i64,
+    feed_id: [u8; 32],
+    amount_premium_ask: u64,
+    is_premium_to_seller: bool,
+    settlement: SettlementType,
+    exercise_style: ExerciseStyle,
+    contracts: u64,
+    max_conf_bps: u16,
+    mark_window: i64,
+    price_source: PriceSource,
 ) -> Result<()> {
+    require!(!ctx.accounts.config.is_write_paused, ErrorCode::Paused);
+
     let clock = Clock::get()?;
 
     require!(
@@ -64,22 +92,72 @@ pub fn handle_initialize(
         ErrorCode::ExpiryIsInThePast
     );
 
+    require!(amount_base > 0 && amount_quote > 0, ErrorCode::ZeroAmount);
+
+    require!(
+        amount_base.checked_rem(contracts) == Some(0)
+            && amount_quote.checked_rem(contracts) == Some(0),
+        ErrorCode::InvalidContracts
+    );
+
+    require!(
+        ctx.accounts.config.is_feed_allowed(&feed_id),
+        ErrorCode::FeedNotAllowed
+    );
+
+    // A tolerance tighter than the mark limit could reject a final mark and lapse the
+    // option to the seller, so it must accept every mark the config does
+    require!(
+        ctx.accounts.config.max_conf_bps <= max_conf_bps && max_conf_bps <= MAX_CONF_BPS,
+        ErrorCode::InvalidConfTolerance
+    );
+
+    // Zero picks the window set in the config
+    let mark_window = if mark_window == 0 {
+        ctx.accounts.config.mark_window
+    } else {
+        mark_window
+    };
+    require!(
+        0 < mark_window && mark_window <= MAX_MARK_WINDOW,
+        ErrorCode::InvalidMarkWindow
+    );
+
+    validate_mint(&ctx.accounts.mint_base)?;
+    validate_mint(&ctx.accounts.mint_quote)?;
+    validate_mint(&ctx.accounts.mint_premium)?;
+
     // Set state
     ctx.accounts.data.set_inner(CoveredCall {
         amount_base,
         amount_premium: None,
+        amount_premium_ask,
         amount_quote,
         bump: ctx.bumps.data,
-        buyer: ctx.accounts.buyer.key(),
+        buyer: ctx.accounts.buyer.as_ref().map(|buyer| buyer.key()),
+        contracts,
+        contracts_exercised: 0,
+        decimals_base: ctx.accounts.mint_base.decimals,
+        decimals_quote: ctx.accounts.mint_quote.decimals,
+        exercise_style,
+        feed_id,
         is_exercised: false,
+        is_premium_to_seller,
+        mark_window,
+        max_conf_bps,
         mint_base: ctx.accounts.mint_base.key(),
+        mint_option: ctx.accounts.mint_option.as_ref().map(|mint| mint.key()),
+        mint_premium: ctx.accounts.mint_premium.key(),
         mint_quote: ctx.accounts.mint_quote.key(),
+        price_source,
         seller: ctx.accounts.seller.key(),
+        settlement,
         timestamp_created: clock.unix_timestamp,
         timestamp_expiry,
     });
 
-    // Transfer base to vault
+    // Transfer base to vault, grossed up so the vault holds the full amount after fees
+    let fee = get_transfer_inverse_fee(&ctx.accounts.mint_base, amount_base)?;
     transfer_checked(
         CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
@@ -90,9 +168,39 @@ pub fn handle_initialize(
                 authority: ctx.accounts.seller.to_account_info(),
             },
         ),
-        amount_base,
+        amount_base
+            .checked_add(fee)
+            .ok_or(ErrorCode::MathOverflow)?,
         ctx.accounts.mint_base.decimals,
     )?;
 
+    emit!(OptionCreated {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Call,
+        seller: ctx.accounts.data.seller,
+        buyer: ctx.accounts.data.buyer,
+        mint_base: ctx.accounts.data.mint_base,
+        mint_quote: ctx.accounts.data.mint_quote,
+        mint_premium: ctx.accounts.data.mint_premium,
+        amount_base,
+        amount_quote,
+        amount_premium_ask,
+        settlement,
+        exercise_style,
+        contracts,
+        max_conf_bps,
+        mark_window,
+        price_source,
+        strike: calc_strike(
+            amount_base,
+            amount_quote,
+            ctx.accounts.mint_base.decimals,
+            ctx.accounts.mint_quote.decimals,
+            PRICE_EXPONENT,
+        )?,
+        timestamp_expiry,
+        feed_id,
+    });
+
     Ok(())
 }
@@ -0,0 +1,64 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+
+use crate::error::ErrorCode;
+use crate::events::ConfigUpdated;
+use crate::state::Config;
+use crate::{DEFAULT_MARK_WINDOW, DEFAULT_MAX_CONF_BPS};
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Config::INIT_SPACE,
+        seeds = [b"config"],
+        bump,
+    )]
+    pub config: Account<'info, Config>,
+    // Only the upgrade authority can appoint the first admin
+    #[account(
+        seeds = [crate::ID.as_ref()],
+        bump,
+        seeds::program = bpf_loader_upgradeable::ID,
+        constraint = program_data.upgrade_authority_address == Some(payer.key())
+            @ ErrorCode::NotUpgradeAuthority,
+    )]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+// Run once by the upgrade authority right after deploy, the admin is expected to be a multisig
+pub fn handle_initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey) -> Result<()> {
+    ctx.accounts.config.set_inner(Config {
+        admin,
+        allowed_feeds: vec![],
+        bump: ctx.bumps.config,
+        fee_bps_premium: 0,
+        fee_bps_settlement: 0,
+        is_buy_paused: false,
+        is_mark_paused: false,
+        is_write_paused: false,
+        mark_window: DEFAULT_MARK_WINDOW,
+        max_conf_bps: DEFAULT_MAX_CONF_BPS,
+        pending_admin: None,
+    });
+
+    let config = &ctx.accounts.config;
+    emit!(ConfigUpdated {
+        config: config.key(),
+        admin,
+        fee_bps_premium: config.fee_bps_premium,
+        fee_bps_settlement: config.fee_bps_settlement,
+        mark_window: config.mark_window,
+        max_conf_bps: config.max_conf_bps,
+        allowed_feeds: config.allowed_feeds.clone(),
+        is_write_paused: config.is_write_paused,
+        is_buy_paused: config.is_buy_paused,
+        is_mark_paused: config.is_mark_paused,
+    });
+
+    Ok(())
+}
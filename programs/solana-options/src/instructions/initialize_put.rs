@@ -0,0 +1,186 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::constants::{MAX_CONF_BPS, MAX_MARK_WINDOW, PRICE_EXPONENT};
+use crate::error::ErrorCode;
+use crate::events::OptionCreated;
+use crate::math::calc_strike;
+use crate::mint::{get_transfer_inverse_fee, validate_mint};
+use crate::state::{
+    CashSecuredPut, Config, ExerciseStyle, OptionKind, PriceSource, SettlementType,
+};
+
+#[derive(Accounts)]
+#[instruction(amount_base: u64, amount_quote: u64, timestamp_expiry: i64, feed_id: [u8; 32], amount_premium_ask: u64, is_premium_to_seller: bool, max_conf_bps: u16, mark_window: i64, price_source: PriceSource)]
+pub struct InitializePut<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    pub buyer: Option<SystemAccount<'info>>,
+    #[account(
+        init,
+        payer = seller,
+        space = 8 + CashSecuredPut::INIT_SPACE,
+        seeds = [
+            b"cash-secured-put",
+            seller.key().as_ref(),
+            mint_base.key().as_ref(),
+            mint_quote.key().as_ref(),
+            amount_base.to_le_bytes().as_ref(),
+            amount_quote.to_le_bytes().as_ref(),
+            timestamp_expiry.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub data: Account<'info, CashSecuredPut>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    pub mint_quote: InterfaceAccount<'info, Mint>,
+    pub mint_premium: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        constraint = ata_seller_quote.amount >= amount_quote,
+        associated_token::mint = mint_quote,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_seller_quote: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = seller,
+        associated_token::mint = mint_quote,
+        associated_token::authority = data,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_quote: InterfaceAccount<'info, TokenAccount>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn handle_initialize_put(
+    ctx: Context<InitializePut>,
+    amount_base: u64,
+    amount_quote: u64,
+    timestamp_expiry: i64,
+    feed_id: [u8; 32],
+    amount_premium_ask: u64,
+    is_premium_to_seller: bool,
+    max_conf_bps: u16,
+    mark_window: i64,
+    price_source: PriceSource,
+) -> Result<()> {
+    require!(!ctx.accounts.config.is_write_paused, ErrorCode::Paused);
+
+    let clock = Clock::get()?;
+
+    require!(
+        timestamp_expiry > clock.unix_timestamp,
+        ErrorCode::ExpiryIsInThePast
+    );
+
+    require!(amount_base > 0 && amount_quote > 0, ErrorCode::ZeroAmount);
+
+    require!(
+        ctx.accounts.config.is_feed_allowed(&feed_id),
+        ErrorCode::FeedNotAllowed
+    );
+
+    // A tolerance tighter than the mark limit could reject a final mark and lapse the
+    // option to the seller, so it must accept every mark the config does
+    require!(
+        ctx.accounts.config.max_conf_bps <= max_conf_bps && max_conf_bps <= MAX_CONF_BPS,
+        ErrorCode::InvalidConfTolerance
+    );
+
+    // Zero picks the window set in the config
+    let mark_window = if mark_window == 0 {
+        ctx.accounts.config.mark_window
+    } else {
+        mark_window
+    };
+    require!(
+        0 < mark_window && mark_window <= MAX_MARK_WINDOW,
+        ErrorCode::InvalidMarkWindow
+    );
+
+    validate_mint(&ctx.accounts.mint_base)?;
+    validate_mint(&ctx.accounts.mint_quote)?;
+    validate_mint(&ctx.accounts.mint_premium)?;
+
+    // Set state
+    ctx.accounts.data.set_inner(CashSecuredPut {
+        amount_base,
+        amount_premium: None,
+        amount_premium_ask,
+        amount_quote,
+        bump: ctx.bumps.data,
+        buyer: ctx.accounts.buyer.as_ref().map(|buyer| buyer.key()),
+        decimals_base: ctx.accounts.mint_base.decimals,
+        decimals_quote: ctx.accounts.mint_quote.decimals,
+        feed_id,
+        is_exercised: false,
+        is_premium_to_seller,
+        mark_window,
+        max_conf_bps,
+        mint_base: ctx.accounts.mint_base.key(),
+        mint_premium: ctx.accounts.mint_premium.key(),
+        mint_quote: ctx.accounts.mint_quote.key(),
+        price_source,
+        seller: ctx.accounts.seller.key(),
+        timestamp_created: clock.unix_timestamp,
+        timestamp_expiry,
+    });
+
+    // Transfer quote to vault, grossed up so the vault holds the full amount after fees
+    let fee = get_transfer_inverse_fee(&ctx.accounts.mint_quote, amount_quote)?;
+    transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.ata_seller_quote.to_account_info(),
+                to: ctx.accounts.ata_vault_quote.to_account_info(),
+                mint: ctx.accounts.mint_quote.to_account_info(),
+                authority: ctx.accounts.seller.to_account_info(),
+            },
+        ),
+        amount_quote
+            .checked_add(fee)
+            .ok_or(ErrorCode::MathOverflow)?,
+        ctx.accounts.mint_quote.decimals,
+    )?;
+
+    emit!(OptionCreated {
+        option: ctx.accounts.data.key(),
+        kind: OptionKind::Put,
+        seller: ctx.accounts.data.seller,
+        buyer: ctx.accounts.data.buyer,
+        mint_base: ctx.accounts.data.mint_base,
+        mint_quote: ctx.accounts.data.mint_quote,
+        mint_premium: ctx.accounts.data.mint_premium,
+        amount_base,
+        amount_quote,
+        amount_premium_ask,
+        settlement: SettlementType::Cash,
+        exercise_style: ExerciseStyle::European,
+        contracts: 1,
+        max_conf_bps,
+        mark_window,
+        price_source,
+        strike: calc_strike(
+            amount_base,
+            amount_quote,
+            ctx.accounts.mint_base.decimals,
+            ctx.accounts.mint_quote.decimals,
+            PRICE_EXPONENT,
+        )?,
+        timestamp_expiry,
+        feed_id,
+    });
+
+    Ok(())
+}
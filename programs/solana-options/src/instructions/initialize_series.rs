@@ -0,0 +1,137 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::constants::PRICE_EXPONENT;
+use crate::error::ErrorCode;
+use crate::events::SeriesCreated;
+use crate::math::calc_strike;
+use crate::mint::validate_mint;
+use crate::state::{Config, ExerciseStyle, OptionSeries};
+
+#[derive(Accounts)]
+#[instruction(amount_base: u64, amount_quote: u64, timestamp_expiry: i64, feed_id: [u8; 32], exercise_style: ExerciseStyle)]
+pub struct InitializeSeries<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + OptionSeries::INIT_SPACE,
+        seeds = [
+            b"option-series",
+            mint_base.key().as_ref(),
+            mint_quote.key().as_ref(),
+            amount_base.to_le_bytes().as_ref(),
+            amount_quote.to_le_bytes().as_ref(),
+            timestamp_expiry.to_le_bytes().as_ref(),
+            feed_id.as_ref(),
+            &[exercise_style as u8],
+        ],
+        bump,
+    )]
+    pub series: Account<'info, OptionSeries>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    pub mint_quote: InterfaceAccount<'info, Mint>,
+    #[account(
+        init,
+        payer = payer,
+        associated_token::mint = mint_base,
+        associated_token::authority = series,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        init,
+        payer = payer,
+        seeds = [b"series-option", series.key().as_ref()],
+        bump,
+        mint::decimals = 0,
+        mint::authority = series,
+        mint::token_program = token_program,
+    )]
+    pub mint_option: InterfaceAccount<'info, Mint>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_initialize_series(
+    ctx: Context<InitializeSeries>,
+    amount_base: u64,
+    amount_quote: u64,
+    timestamp_expiry: i64,
+    feed_id: [u8; 32],
+    exercise_style: ExerciseStyle,
+) -> Result<()> {
+    require!(!ctx.accounts.config.is_write_paused, ErrorCode::Paused);
+
+    let clock = Clock::get()?;
+
+    require!(
+        timestamp_expiry > clock.unix_timestamp,
+        ErrorCode::ExpiryIsInThePast
+    );
+
+    require!(amount_base > 0 && amount_quote > 0, ErrorCode::ZeroAmount);
+
+    require!(
+        ctx.accounts.config.is_feed_allowed(&feed_id),
+        ErrorCode::FeedNotAllowed
+    );
+
+    validate_mint(&ctx.accounts.mint_base)?;
+    validate_mint(&ctx.accounts.mint_quote)?;
+
+    let strike = calc_strike(
+        amount_base,
+        amount_quote,
+        ctx.accounts.mint_base.decimals,
+        ctx.accounts.mint_quote.decimals,
+        PRICE_EXPONENT,
+    )?;
+
+    ctx.accounts.series.set_inner(OptionSeries {
+        amount_base,
+        amount_base_paid: 0,
+        amount_quote,
+        bump: ctx.bumps.series,
+        contracts_exercised: 0,
+        contracts_sold: 0,
+        contracts_written: 0,
+        decimals_base: ctx.accounts.mint_base.decimals,
+        decimals_quote: ctx.accounts.mint_quote.decimals,
+        exercise_style,
+        exponent_settled: 0,
+        feed_id,
+        is_lapsed: false,
+        mark_window: ctx.accounts.config.mark_window,
+        max_conf_bps: ctx.accounts.config.max_conf_bps,
+        mint_base: ctx.accounts.mint_base.key(),
+        mint_option: ctx.accounts.mint_option.key(),
+        mint_quote: ctx.accounts.mint_quote.key(),
+        price_settled: 0,
+        timestamp_expiry,
+    });
+
+    emit!(SeriesCreated {
+        series: ctx.accounts.series.key(),
+        mint_base: ctx.accounts.series.mint_base,
+        mint_quote: ctx.accounts.series.mint_quote,
+        mint_option: ctx.accounts.series.mint_option,
+        amount_base,
+        amount_quote,
+        strike,
+        timestamp_expiry,
+        feed_id,
+        exercise_style,
+        max_conf_bps: ctx.accounts.series.max_conf_bps,
+        mark_window: ctx.accounts.series.mark_window,
+    });
+
+    Ok(())
+}
@@ -1,11 +1,14 @@
 use anchor_lang::prelude::*;
 
 use crate::error::ErrorCode;
-use crate::ExpiryData;
-use pyth_solana_receiver_sdk::price_update::{get_feed_id_from_hex, PriceUpdateV2};
+use crate::events::OptionMarked;
+use crate::math::calc_conf_bps;
+use crate::state::Config;
+use crate::{ExpiryData, MAX_MARK_WINDOW, TWAP_WINDOW};
+use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 
 #[derive(Accounts)]
-#[instruction(timestamp_expiry: i64)]
+#[instruction(feed_id: [u8; 32], timestamp_expiry: i64)]
 pub struct Mark<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -16,28 +19,36 @@ pub struct Mark<'info> {
       space = 8 + ExpiryData::INIT_SPACE,
       seeds = [
           "expiry-meta".as_bytes(),
+          feed_id.as_ref(),
           timestamp_expiry.to_le_bytes().as_ref(),
       ],
       bump,
   )]
     pub expiry: Account<'info, ExpiryData>,
     pub price_update: Account<'info, PriceUpdateV2>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn handle_mark(ctx: Context<Mark>, expiry: i64) -> Result<()> {
+pub fn handle_mark(ctx: Context<Mark>, feed_id: [u8; 32], expiry: i64) -> Result<()> {
+    require!(!ctx.accounts.config.is_mark_paused, ErrorCode::Paused);
+
     let price_update = &mut ctx.accounts.price_update;
 
-    let window: i64 = 30 * 60; // Allow prices in this time before expiry
+    // Allow prices in the widest window, options check their own window when settling
+    let window = MAX_MARK_WINDOW;
     let clock = Clock::get()?;
 
+    require!(
+        !ExpiryData::is_final(expiry, clock.unix_timestamp),
+        ErrorCode::MarkFinalized
+    );
+
     let maximum_age: u64 = (clock.unix_timestamp - (expiry - window))
         .try_into()
         .unwrap_or_else(|_| window.try_into().unwrap());
 
-    let feed_id: [u8; 32] =
-        get_feed_id_from_hex("0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d")?;
-
     let price = price_update.get_price_no_older_than(&clock, maximum_age, &feed_id)?;
 
     require!(
@@ -45,6 +56,11 @@ pub fn handle_mark(ctx: Context<Mark>, expiry: i64) -> Result<()> {
         ErrorCode::PriceIrrelevant,
     );
 
+    require!(
+        calc_conf_bps(price.conf, price.price) <= u64::from(ctx.accounts.config.max_conf_bps),
+        ErrorCode::ConfidenceTooWide
+    );
+
     // Ensure updated price is more recent
     require!(
         price.publish_time >= ctx.accounts.expiry.publish_time,
@@ -58,6 +74,31 @@ pub fn handle_mark(ctx: Context<Mark>, expiry: i64) -> Result<()> {
         ctx.accounts.expiry.payer
     };
 
+    // Sample into the TWAP, each newer print closes the interval of the previous one
+    let previous = &ctx.accounts.expiry;
+    let (twap_sum, twap_count, twap_first_publish_time) =
+        if price.publish_time <= expiry - TWAP_WINDOW {
+            (0, 0, 0)
+        } else if previous.twap_count == 0 || previous.exponent != price.exponent {
+            (0, 1, price.publish_time)
+        } else if previous.publish_time == price.publish_time {
+            (
+                previous.twap_sum,
+                previous.twap_count,
+                previous.twap_first_publish_time,
+            )
+        } else {
+            let twap_sum = i128::from(previous.price)
+                .checked_mul(i128::from(price.publish_time - previous.publish_time))
+                .and_then(|interval| previous.twap_sum.checked_add(interval))
+                .ok_or(ErrorCode::MathOverflow)?;
+            let twap_count = previous
+                .twap_count
+                .checked_add(1)
+                .ok_or(ErrorCode::MathOverflow)?;
+            (twap_sum, twap_count, previous.twap_first_publish_time)
+        };
+
     ctx.accounts.expiry.set_inner(ExpiryData {
         price: price.price,
         conf: price.conf,
@@ -65,6 +106,22 @@ pub fn handle_mark(ctx: Context<Mark>, expiry: i64) -> Result<()> {
         publish_time: price.publish_time,
         bump: ctx.bumps.expiry,
         payer,
+        feed_id,
+        twap_sum,
+        twap_count,
+        twap_first_publish_time,
+        ref_count: ctx.accounts.expiry.ref_count,
+    });
+
+    emit!(OptionMarked {
+        expiry: ctx.accounts.expiry.key(),
+        feed_id,
+        timestamp_expiry: expiry,
+        price: price.price,
+        conf: price.conf,
+        exponent: price.exponent,
+        publish_time: price.publish_time,
     });
+
     Ok(())
 }
@@ -1,9 +1,10 @@
 use anchor_lang::prelude::*;
 
+use crate::events::MarkClosed;
 use crate::ExpiryData;
 
 #[derive(Accounts)]
-#[instruction(timestamp_expiry: i64)]
+#[instruction(feed_id: [u8; 32], timestamp_expiry: i64)]
 pub struct MarkClose<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
@@ -11,6 +12,7 @@ pub struct MarkClose<'info> {
       mut,
       seeds = [
           "expiry-meta".as_bytes(),
+          feed_id.as_ref(),
           timestamp_expiry.to_le_bytes().as_ref(),
       ],
       bump = expiry.bump,
@@ -20,10 +22,22 @@ pub struct MarkClose<'info> {
     pub system_program: Program<'info, System>,
 }
 
-pub fn handle_mark_close(ctx: Context<MarkClose>, _expiry: i64) -> Result<()> {
+pub fn handle_mark_close(ctx: Context<MarkClose>, feed_id: [u8; 32], expiry: i64) -> Result<()> {
     require!(
         ctx.accounts.payer.key() == ctx.accounts.expiry.payer,
         ErrorCode::ConstraintOwner,
     );
+    require!(
+        ctx.accounts.expiry.ref_count == 0,
+        crate::error::ErrorCode::MarkInUse
+    );
+
+    emit!(MarkClosed {
+        expiry: ctx.accounts.expiry.key(),
+        feed_id,
+        timestamp_expiry: expiry,
+        payer: ctx.accounts.payer.key(),
+    });
+
     Ok(())
 }
@@ -1,13 +1,47 @@
+pub mod accept_admin;
 pub mod buy;
+pub mod buy_put;
+pub mod buy_series;
+pub mod cancel;
+pub mod cancel_put;
 pub mod close;
+pub mod close_put;
 pub mod exercise;
+pub mod exercise_put;
+pub mod exercise_series;
 pub mod initialize;
+pub mod initialize_config;
+pub mod initialize_put;
+pub mod initialize_series;
 pub mod mark;
 pub mod mark_close;
+pub mod transfer_admin;
+pub mod transfer_position;
+pub mod update_config;
+pub mod withdraw_series;
+pub mod withdraw_treasury;
+pub mod write_series;
 
+pub use accept_admin::*;
 pub use buy::*;
+pub use buy_put::*;
+pub use buy_series::*;
+pub use cancel::*;
+pub use cancel_put::*;
 pub use close::*;
+pub use close_put::*;
 pub use exercise::*;
+pub use exercise_put::*;
+pub use exercise_series::*;
 pub use initialize::*;
+pub use initialize_config::*;
+pub use initialize_put::*;
+pub use initialize_series::*;
 pub use mark::*;
 pub use mark_close::*;
+pub use transfer_admin::*;
+pub use transfer_position::*;
+pub use update_config::*;
+pub use withdraw_series::*;
+pub use withdraw_treasury::*;
+pub use write_series::*;
@@ -0,0 +1,29 @@
+use anchor_lang::prelude::*;
+
+use crate::events::AdminProposed;
+use crate::state::Config;
+
+#[derive(Accounts)]
+pub struct TransferAdmin<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+// First step of the handover, the new admin has to accept before it takes effect
+pub fn handle_transfer_admin(ctx: Context<TransferAdmin>, pending_admin: Pubkey) -> Result<()> {
+    ctx.accounts.config.pending_admin = Some(pending_admin);
+
+    emit!(AdminProposed {
+        config: ctx.accounts.config.key(),
+        admin: ctx.accounts.admin.key(),
+        pending_admin,
+    });
+
+    Ok(())
+}
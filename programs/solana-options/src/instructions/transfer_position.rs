@@ -0,0 +1,59 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::PositionTransferred;
+use crate::state::CoveredCall;
+
+#[derive(Accounts)]
+pub struct TransferPosition<'info> {
+    #[account(constraint = data.buyer == Some(buyer.key()))]
+    pub buyer: Signer<'info>,
+    pub new_buyer: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "covered-call".as_bytes(),
+            data.seller.as_ref(),
+            data.mint_base.as_ref(),
+            data.mint_quote.as_ref(),
+            &data.amount_base.to_le_bytes(),
+            &data.amount_quote.to_le_bytes(),
+            &data.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = data.bump,
+    )]
+    pub data: Account<'info, CoveredCall>,
+}
+
+// Hands every live contract to the new buyer. A single buyer holds the position, so
+// splitting it between holders goes through the tokenized mode instead
+pub fn handle_transfer_position(ctx: Context<TransferPosition>) -> Result<()> {
+    let clock = Clock::get()?;
+
+    require!(
+        clock.unix_timestamp < ctx.accounts.data.timestamp_expiry,
+        ErrorCode::OptionExpired
+    );
+
+    require!(
+        ctx.accounts.data.amount_premium.is_some(),
+        ErrorCode::OptionNotPurchased
+    );
+
+    // Tokenized positions change hands by transferring the option token
+    require!(
+        ctx.accounts.data.mint_option.is_none(),
+        ErrorCode::OptionIsTokenized
+    );
+
+    ctx.accounts.data.buyer = Some(ctx.accounts.new_buyer.key());
+
+    emit!(PositionTransferred {
+        option: ctx.accounts.data.key(),
+        from: ctx.accounts.buyer.key(),
+        to: ctx.accounts.new_buyer.key(),
+        contracts: ctx.accounts.data.contracts - ctx.accounts.data.contracts_exercised,
+    });
+
+    Ok(())
+}
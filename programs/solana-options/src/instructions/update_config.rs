@@ -0,0 +1,74 @@
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+use crate::events::ConfigUpdated;
+use crate::state::Config;
+use crate::{MAX_ALLOWED_FEEDS, MAX_CONF_BPS, MAX_FEE_BPS, MAX_MARK_WINDOW};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ConfigParams {
+    pub fee_bps_premium: u16,
+    pub fee_bps_settlement: u16,
+    pub mark_window: i64,
+    pub max_conf_bps: u16,
+    pub allowed_feeds: Vec<[u8; 32]>,
+    pub is_write_paused: bool,
+    pub is_buy_paused: bool,
+    pub is_mark_paused: bool,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [b"config"],
+        bump = config.bump,
+        has_one = admin,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+pub fn handle_update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+    require!(
+        params.fee_bps_premium <= MAX_FEE_BPS && params.fee_bps_settlement <= MAX_FEE_BPS,
+        ErrorCode::InvalidFee
+    );
+    require!(
+        0 < params.mark_window && params.mark_window <= MAX_MARK_WINDOW,
+        ErrorCode::InvalidMarkWindow
+    );
+    require!(
+        0 < params.max_conf_bps && params.max_conf_bps <= MAX_CONF_BPS,
+        ErrorCode::InvalidConfTolerance
+    );
+    require!(
+        params.allowed_feeds.len() <= MAX_ALLOWED_FEEDS,
+        ErrorCode::TooManyFeeds
+    );
+
+    let config = &mut ctx.accounts.config;
+    config.fee_bps_premium = params.fee_bps_premium;
+    config.fee_bps_settlement = params.fee_bps_settlement;
+    config.mark_window = params.mark_window;
+    config.max_conf_bps = params.max_conf_bps;
+    config.allowed_feeds = params.allowed_feeds.clone();
+    config.is_write_paused = params.is_write_paused;
+    config.is_buy_paused = params.is_buy_paused;
+    config.is_mark_paused = params.is_mark_paused;
+
+    emit!(ConfigUpdated {
+        config: config.key(),
+        admin: config.admin,
+        fee_bps_premium: params.fee_bps_premium,
+        fee_bps_settlement: params.fee_bps_settlement,
+        mark_window: params.mark_window,
+        max_conf_bps: params.max_conf_bps,
+        allowed_feeds: params.allowed_feeds,
+        is_write_paused: params.is_write_paused,
+        is_buy_paused: params.is_buy_paused,
+        is_mark_paused: params.is_mark_paused,
+    });
+
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::events::SeriesWithdrawn;
+use crate::math::{calc_strike, get_settlements};
+use crate::state::{Config, OptionSeries, PriceSource, SeriesWriter};
+use crate::{error::ErrorCode, ExpiryData};
+
+#[derive(Accounts)]
+pub struct WithdrawSeries<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "option-series".as_bytes(),
+            series.mint_base.as_ref(),
+            series.mint_quote.as_ref(),
+            &series.amount_base.to_le_bytes(),
+            &series.amount_quote.to_le_bytes(),
+            &series.timestamp_expiry.to_le_bytes(),
+            series.feed_id.as_ref(),
+            &[series.exercise_style as u8],
+        ],
+        bump = series.bump,
+    )]
+    pub series: Account<'info, OptionSeries>,
+    #[account(
+        mut,
+        seeds = [
+            "series-writer".as_bytes(),
+            series.key().as_ref(),
+            seller.key().as_ref(),
+        ],
+        bump = writer.bump,
+    )]
+    pub writer: Account<'info, SeriesWriter>,
+    #[account(
+        mut,
+        seeds = [
+          "expiry-meta".as_bytes(),
+          series.feed_id.as_ref(),
+          &series.timestamp_expiry.to_le_bytes(),
+        ],
+        bump = expiry.bump,
+        constraint = expiry.feed_id == series.feed_id,
+    )]
+    pub expiry: Option<Account<'info, ExpiryData>>,
+    #[account(constraint = mint_base.key() == series.mint_base)]
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        associated_token::mint = mint_base,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_seller_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_base,
+        associated_token::authority = series,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+// Unsold collateral can be withdrawn at any time, sold collateral only after expiry
+pub fn handle_withdraw_series(ctx: Context<WithdrawSeries>) -> Result<()> {
+    let clock = Clock::get()?;
+    let is_expired = clock.unix_timestamp >= ctx.accounts.series.timestamp_expiry;
+
+    let can_lapse = ExpiryData::can_lapse(
+        ctx.accounts.series.timestamp_expiry,
+        clock.unix_timestamp,
+        &ctx.accounts.config,
+    );
+
+    // Settle the series against the mark once, after which the expiry can be closed
+    let series = &mut ctx.accounts.series;
+    if is_expired
+        && ctx.accounts.writer.contracts_sold > 0
+        && series.price_settled == 0
+        && !series.is_lapsed
+    {
+        let Some(expiry) = ctx.accounts.expiry.as_mut() else {
+            return err!(ErrorCode::OptionNotMarked);
+        };
+        match expiry.settlement_price(
+            series.timestamp_expiry,
+            series.mark_window,
+            series.max_conf_bps,
+            PriceSource::Mark,
+            clock.unix_timestamp,
+        ) {
+            Ok(price) => {
+                series.price_settled = price;
+                series.exponent_settled = expiry.exponent;
+            }
+            // Without a price to settle at, open contracts lapse once the grace period has
+            // passed, and stay lapsed so a later mark cannot claim collateral already paid out
+            Err(_) if can_lapse => series.is_lapsed = true,
+            Err(err) => return Err(err),
+        }
+        expiry.release();
+    }
+
+    let series = &ctx.accounts.series;
+    let writer = &ctx.accounts.writer;
+    let contracts_unsold = writer.contracts_written - writer.contracts_sold;
+    let mut amount = u128::from(contracts_unsold) * u128::from(series.amount_base);
+
+    let contracts_sold = if is_expired { writer.contracts_sold } else { 0 };
+    if contracts_sold > 0 {
+        let amount_buyer = if series.is_lapsed {
+            0
+        } else {
+            let strike = calc_strike(
+                series.amount_base,
+                series.amount_quote,
+                series.decimals_base,
+                series.decimals_quote,
+                series.exponent_settled,
+            )?;
+            let [_, amount_buyer] =
+                get_settlements(strike, series.price_settled, series.amount_base);
+            amount_buyer
+        };
+
+        // Sold collateral is pooled, so every writer bears the same share of what
+        // holders have been paid or can still claim at the mark
+        let contracts_open = series.contracts_sold - series.contracts_exercised;
+        let pool = u128::from(series.contracts_sold) * u128::from(series.amount_base)
+            - u128::from(series.amount_base_paid)
+            - u128::from(contracts_open) * u128::from(amount_buyer);
+        amount += pool * u128::from(contracts_sold) / u128::from(series.contracts_sold);
+    }
+    let amount = u64::try_from(amount).map_err(|_| error!(ErrorCode::MathOverflow))?;
+
+    let style = [series.exercise_style as u8];
+    let seeds = [
+        "option-series".as_bytes(),
+        series.mint_base.as_ref(),
+        series.mint_quote.as_ref(),
+        &series.amount_base.to_le_bytes(),
+        &series.amount_quote.to_le_bytes(),
+        &series.timestamp_expiry.to_le_bytes(),
+        series.feed_id.as_ref(),
+        &style,
+        &[series.bump],
+    ];
+    let signer = &[&seeds[..]];
+
+    if amount > 0 {
+        transfer_checked(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.ata_vault_base.to_account_info(),
+                    to: ctx.accounts.ata_seller_base.to_account_info(),
+                    mint: ctx.accounts.mint_base.to_account_info(),
+                    authority: ctx.accounts.series.to_account_info(),
+                },
+                signer,
+            ),
+            amount,
+            ctx.accounts.mint_base.decimals,
+        )?;
+    }
+
+    emit!(SeriesWithdrawn {
+        series: ctx.accounts.series.key(),
+        seller: ctx.accounts.seller.key(),
+        contracts_unsold,
+        contracts_sold,
+        amount_base: amount,
+        is_lapsed: ctx.accounts.series.is_lapsed,
+    });
+
+    // Sold contracts stay with the writer until expiry
+    ctx.accounts.series.contracts_written -= contracts_unsold;
+    ctx.accounts.writer.contracts_written -= contracts_unsold;
+    if contracts_sold == ctx.accounts.writer.contracts_sold {
+        ctx.accounts
+            .writer
+            .close(ctx.accounts.seller.to_account_info())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::error::ErrorCode;
+use crate::events::SeriesWritten;
+use crate::mint::{get_transfer_inverse_fee, validate_mint};
+use crate::state::{Config, OptionSeries, SeriesWriter};
+
+#[derive(Accounts)]
+pub struct WriteSeries<'info> {
+    #[account(mut)]
+    pub seller: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [
+            "option-series".as_bytes(),
+            series.mint_base.as_ref(),
+            series.mint_quote.as_ref(),
+            &series.amount_base.to_le_bytes(),
+            &series.amount_quote.to_le_bytes(),
+            &series.timestamp_expiry.to_le_bytes(),
+            series.feed_id.as_ref(),
+            &[series.exercise_style as u8],
+        ],
+        bump = series.bump,
+    )]
+    pub series: Account<'info, OptionSeries>,
+    #[account(
+        init_if_needed,
+        payer = seller,
+        space = 8 + SeriesWriter::INIT_SPACE,
+        seeds = [
+            "series-writer".as_bytes(),
+            series.key().as_ref(),
+            seller.key().as_ref(),
+        ],
+        bump,
+    )]
+    pub writer: Account<'info, SeriesWriter>,
+    #[account(constraint = mint_base.key() == series.mint_base)]
+    pub mint_base: InterfaceAccount<'info, Mint>,
+    pub mint_premium: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
+        associated_token::mint = mint_base,
+        associated_token::authority = seller,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_seller_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        associated_token::mint = mint_base,
+        associated_token::authority = series,
+        associated_token::token_program = token_program,
+    )]
+    pub ata_vault_base: InterfaceAccount<'info, TokenAccount>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn handle_write_series(
+    ctx: Context<WriteSeries>,
+    contracts: u64,
+    amount_premium_ask: u64,
+) -> Result<()> {
+    require!(!ctx.accounts.config.is_write_paused, ErrorCode::Paused);
+
+    let clock = Clock::get()?;
+
+    require!(
+        clock.unix_timestamp < ctx.accounts.series.timestamp_expiry,
+        ErrorCode::OptionExpired
+    );
+    require!(contracts > 0, ErrorCode::InvalidQuantity);
+
+    validate_mint(&ctx.accounts.mint_premium)?;
+
+    let series = &mut ctx.accounts.series;
+    let writer = &mut ctx.accounts.writer;
+
+    // The ask applies to every unsold contract of the writer
+    writer.series = series.key();
+    writer.seller = ctx.accounts.seller.key();
+    writer.mint_premium = ctx.accounts.mint_premium.key();
+    writer.amount_premium_ask = amount_premium_ask;
+    writer.bump = ctx.bumps.writer;
+    writer.contracts_written = writer
+        .contracts_written
+        .checked_add(contracts)
+        .ok_or(ErrorCode::MathOverflow)?;
+    series.contracts_written = series
+        .contracts_written
+        .checked_add(contracts)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    // Transfer collateral to vault, grossed up so the vault holds the full amount after fees
+    let amount_base = series
+        .amount_base
+        .checked_mul(contracts)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let fee = get_transfer_inverse_fee(&ctx.accounts.mint_base, amount_base)?;
+    transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.ata_seller_base.to_account_info(),
+                to: ctx.accounts.ata_vault_base.to_account_info(),
+                mint: ctx.accounts.mint_base.to_account_info(),
+                authority: ctx.accounts.seller.to_account_info(),
+            },
+        ),
+        amount_base
+            .checked_add(fee)
+            .ok_or(ErrorCode::MathOverflow)?,
+        ctx.accounts.mint_base.decimals,
+    )?;
+
+    emit!(SeriesWritten {
+        series: ctx.accounts.series.key(),
+        seller: ctx.accounts.seller.key(),
+        contracts,
+        mint_premium: ctx.accounts.mint_premium.key(),
+        amount_premium_ask,
+    });
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 pub mod constants;
 pub mod error;
+pub mod events;
 pub mod instructions;
 pub mod math;
+pub mod mint;
 pub mod state;
 
 use anchor_lang::prelude::*;
@@ -16,32 +18,169 @@ declare_id!("So1ar1uyyJ2bhm4DTN3M2wWkug4trVknn2kdZ2vD2Vh");
 pub mod solana_options {
     use super::*;
 
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        handle_accept_admin(ctx)
+    }
+
     pub fn buy(ctx: Context<Buy>, amount_premium: u64) -> Result<()> {
         handle_buy(ctx, amount_premium)
     }
 
+    pub fn buy_put(ctx: Context<BuyPut>, amount_premium: u64) -> Result<()> {
+        handle_buy_put(ctx, amount_premium)
+    }
+
+    pub fn buy_series(ctx: Context<BuySeries>, contracts: u64, amount_premium: u64) -> Result<()> {
+        handle_buy_series(ctx, contracts, amount_premium)
+    }
+
+    pub fn cancel(ctx: Context<Cancel>, reason: String) -> Result<()> {
+        handle_cancel(ctx, reason)
+    }
+
+    pub fn cancel_put(ctx: Context<CancelPut>, reason: String) -> Result<()> {
+        handle_cancel_put(ctx, reason)
+    }
+
     pub fn close(ctx: Context<Close>) -> Result<()> {
         handle_close(ctx)
     }
 
-    pub fn exercise(ctx: Context<Exercise>) -> Result<()> {
-        handle_exercise(ctx)
+    pub fn close_put(ctx: Context<ClosePut>) -> Result<()> {
+        handle_close_put(ctx)
+    }
+
+    pub fn exercise(ctx: Context<Exercise>, quantity: u64) -> Result<()> {
+        handle_exercise(ctx, quantity)
     }
 
+    pub fn exercise_put(ctx: Context<ExercisePut>) -> Result<()> {
+        handle_exercise_put(ctx)
+    }
+
+    pub fn exercise_series(ctx: Context<ExerciseSeries>, quantity: u64) -> Result<()> {
+        handle_exercise_series(ctx, quantity)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         ctx: Context<Initialize>,
         amount_base: u64,
         amount_quote: u64,
         timestamp_expiry: i64,
+        feed_id: [u8; 32],
+        amount_premium_ask: u64,
+        is_premium_to_seller: bool,
+        settlement: SettlementType,
+        exercise_style: ExerciseStyle,
+        contracts: u64,
+        max_conf_bps: u16,
+        mark_window: i64,
+        price_source: PriceSource,
     ) -> Result<()> {
-        handle_initialize(ctx, amount_base, amount_quote, timestamp_expiry)
+        handle_initialize(
+            ctx,
+            amount_base,
+            amount_quote,
+            timestamp_expiry,
+            feed_id,
+            amount_premium_ask,
+            is_premium_to_seller,
+            settlement,
+            exercise_style,
+            contracts,
+            max_conf_bps,
+            mark_window,
+            price_source,
+        )
     }
 
-    pub fn mark_close(ctx: Context<MarkClose>, timestamp_expiry: i64) -> Result<()> {
-        handle_mark_close(ctx, timestamp_expiry)
+    pub fn initialize_config(ctx: Context<InitializeConfig>, admin: Pubkey) -> Result<()> {
+        handle_initialize_config(ctx, admin)
     }
 
-    pub fn mark(ctx: Context<Mark>, timestamp_expiry: i64) -> Result<()> {
-        handle_mark(ctx, timestamp_expiry)
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_put(
+        ctx: Context<InitializePut>,
+        amount_base: u64,
+        amount_quote: u64,
+        timestamp_expiry: i64,
+        feed_id: [u8; 32],
+        amount_premium_ask: u64,
+        is_premium_to_seller: bool,
+        max_conf_bps: u16,
+        mark_window: i64,
+        price_source: PriceSource,
+    ) -> Result<()> {
+        handle_initialize_put(
+            ctx,
+            amount_base,
+            amount_quote,
+            timestamp_expiry,
+            feed_id,
+            amount_premium_ask,
+            is_premium_to_seller,
+            max_conf_bps,
+            mark_window,
+            price_source,
+        )
+    }
+
+    pub fn initialize_series(
+        ctx: Context<InitializeSeries>,
+        amount_base: u64,
+        amount_quote: u64,
+        timestamp_expiry: i64,
+        feed_id: [u8; 32],
+        exercise_style: ExerciseStyle,
+    ) -> Result<()> {
+        handle_initialize_series(
+            ctx,
+            amount_base,
+            amount_quote,
+            timestamp_expiry,
+            feed_id,
+            exercise_style,
+        )
+    }
+
+    pub fn mark_close(
+        ctx: Context<MarkClose>,
+        feed_id: [u8; 32],
+        timestamp_expiry: i64,
+    ) -> Result<()> {
+        handle_mark_close(ctx, feed_id, timestamp_expiry)
+    }
+
+    pub fn mark(ctx: Context<Mark>, feed_id: [u8; 32], timestamp_expiry: i64) -> Result<()> {
+        handle_mark(ctx, feed_id, timestamp_expiry)
+    }
+
+    pub fn transfer_admin(ctx: Context<TransferAdmin>, pending_admin: Pubkey) -> Result<()> {
+        handle_transfer_admin(ctx, pending_admin)
+    }
+
+    pub fn transfer_position(ctx: Context<TransferPosition>) -> Result<()> {
+        handle_transfer_position(ctx)
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+        handle_update_config(ctx, params)
+    }
+
+    pub fn withdraw_series(ctx: Context<WithdrawSeries>) -> Result<()> {
+        handle_withdraw_series(ctx)
+    }
+
+    pub fn withdraw_treasury(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+        handle_withdraw_treasury(ctx, amount)
+    }
+
+    pub fn write_series(
+        ctx: Context<WriteSeries>,
+        contracts: u64,
+        amount_premium_ask: u64,
+    ) -> Result<()> {
+        handle_write_series(ctx, contracts, amount_premium_ask)
     }
 }
@@ -1,10 +1,76 @@
-// Has 8 decimal precision to match pyth price oracle
-pub fn calc_strike(base: u64, quote: u64) -> i64 {
+use anchor_lang::prelude::*;
+
+use crate::error::ErrorCode;
+
+// Price of one whole base token in whole quote tokens, scaled to the oracle exponent
+pub fn calc_strike(
+    base: u64,
+    quote: u64,
+    decimals_base: u8,
+    decimals_quote: u8,
+    exponent: i32,
+) -> Result<i64> {
     // Convert to u128 to avoid overflow and maintain precision
     let base = u128::from(base);
     let quote = u128::from(quote);
-    let result = (quote * 10u128.pow(8 + 3) / base) as u64;
-    result.try_into().unwrap()
+    let scale = i32::from(decimals_base) - i32::from(decimals_quote) - exponent;
+    let factor = 10u128
+        .checked_pow(scale.unsigned_abs())
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let result = if scale >= 0 {
+        quote
+            .checked_mul(factor)
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(base)
+            .ok_or(ErrorCode::MathOverflow)?
+    } else {
+        quote
+            .checked_div(base.checked_mul(factor).ok_or(ErrorCode::MathOverflow)?)
+            .ok_or(ErrorCode::MathOverflow)?
+    };
+
+    i64::try_from(result).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// Confidence interval as basis points of the price, non-positive prices are never confident
+pub fn calc_conf_bps(conf: u64, price: i64) -> u64 {
+    if price <= 0 {
+        return u64::MAX;
+    }
+    (u128::from(conf) * 10_000 / u128::from(price.unsigned_abs()))
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
+
+// Time weighted average where the last price is held from its publish time up to expiry
+pub fn calc_twap(
+    sum: i128,
+    price_last: i64,
+    publish_time_first: i64,
+    publish_time_last: i64,
+    timestamp_expiry: i64,
+) -> Result<i64> {
+    let span = i128::from(timestamp_expiry - publish_time_first);
+    require!(span > 0, ErrorCode::TwapNotReady);
+    let sum = i128::from(price_last)
+        .checked_mul(i128::from(timestamp_expiry - publish_time_last))
+        .and_then(|last| sum.checked_add(last))
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    i64::try_from(sum / span).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// Basis points of `amount`, rounded down
+pub fn calc_fee(amount: u64, bps: u16) -> u64 {
+    (u128::from(amount) * u128::from(bps) / 10_000) as u64
+}
+
+// Share of `amount` covered by `quantity` out of `contracts`, which must divide `amount` evenly
+pub fn get_contracts_amount(amount: u64, quantity: u64, contracts: u64) -> Result<u64> {
+    (amount / contracts)
+        .checked_mul(quantity)
+        .ok_or(error!(ErrorCode::MathOverflow))
 }
 
 pub fn get_settlements(strike: i64, mark: i64, amount: u64) -> [u64; 2] {
@@ -19,16 +85,106 @@ pub fn get_settlements(strike: i64, mark: i64, amount: u64) -> [u64; 2] {
     [seller, amount - seller]
 }
 
+// Settles a put where the collateral is quote, pays the buyer when the mark is below strike
+pub fn get_put_settlements(strike: i64, mark: i64, amount: u64) -> [u64; 2] {
+    if mark >= strike {
+        return [amount, 0];
+    }
+    // Convert to u128 to avoid overflow and maintain precision
+    // Round down the seller
+    let seller = (u128::from(amount) * u128::from(mark.max(0).unsigned_abs())
+        / u128::from(strike.unsigned_abs())) as u64;
+
+    [seller, amount - seller]
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::math::{calc_strike, get_settlements};
+    use crate::math::{
+        calc_conf_bps, calc_fee, calc_strike, calc_twap, get_contracts_amount, get_put_settlements,
+        get_settlements,
+    };
 
     #[test]
     fn test_calc_strike() {
-        assert_eq!(calc_strike(1000, 3500), 3500_0000_0000);
-        assert_eq!(calc_strike(1_000_000_000, 130_000_000), 130_0000_0000);
-        assert_eq!(calc_strike(1_000_000_000, 130_500_000), 130_5000_0000);
-        assert_eq!(calc_strike(10000000, 1370000000000), 137000000_0000_0000); // Created this strike with incorrect decimals
+        // 9 decimal base, 6 decimal quote
+        assert_eq!(calc_strike(1000, 3500, 9, 6, -8).unwrap(), 3500_0000_0000);
+        assert_eq!(
+            calc_strike(1_000_000_000, 130_000_000, 9, 6, -8).unwrap(),
+            130_0000_0000
+        );
+        assert_eq!(
+            calc_strike(1_000_000_000, 130_500_000, 9, 6, -8).unwrap(),
+            130_5000_0000
+        );
+
+        // 8 decimal base, 6 decimal quote
+        assert_eq!(
+            calc_strike(10000000, 1370000000000, 8, 6, -8).unwrap(),
+            1370_0000_0000_0000
+        );
+        // 6 decimal base, 9 decimal quote
+        assert_eq!(
+            calc_strike(1_000_000, 2_500_000_000, 6, 9, -8).unwrap(),
+            2_5000_0000
+        );
+        // Other oracle exponents
+        assert_eq!(
+            calc_strike(1_000_000_000, 130_500_000, 9, 6, -5).unwrap(),
+            130_50000
+        );
+        assert_eq!(
+            calc_strike(1_000_000_000, 130_500_000, 9, 6, 1).unwrap(),
+            13
+        );
+
+        // Overflows are errors
+        assert!(calc_strike(1, u64::MAX, 18, 0, -18).is_err());
+        // A zero base is an error rather than a panic
+        assert!(calc_strike(0, 3500, 9, 6, -8).is_err());
+        assert!(calc_strike(0, 3500, 6, 9, -8).is_err());
+    }
+
+    #[test]
+    fn test_calc_conf_bps() {
+        assert_eq!(calc_conf_bps(12190053, 130_0000_0000), 9);
+        assert_eq!(calc_conf_bps(1_0000_0000, 100_0000_0000), 100);
+        assert_eq!(calc_conf_bps(0, 100_0000_0000), 0);
+        // Can handle a non-positive price
+        assert_eq!(calc_conf_bps(1, 0), u64::MAX);
+        assert_eq!(calc_conf_bps(1, -5), u64::MAX);
+    }
+
+    #[test]
+    fn test_calc_fee() {
+        assert_eq!(calc_fee(1_000, 50), 5);
+        assert_eq!(calc_fee(1_000, 0), 0);
+        // Rounds down in favour of the user
+        assert_eq!(calc_fee(199, 50), 0);
+        assert_eq!(calc_fee(u64::MAX, 10_000), u64::MAX);
+    }
+
+    #[test]
+    fn test_calc_twap() {
+        // 100 for 60s, 110 for 30s, then 130 for the last 10s up to expiry
+        assert_eq!(
+            calc_twap(100 * 60 + 110 * 30, 130, 0, 90, 100).unwrap(),
+            106
+        );
+        // A single sample at expiry has no span to weight
+        assert!(calc_twap(0, 130, 100, 100, 100).is_err());
+        // A single sample before expiry is its own average
+        assert_eq!(calc_twap(0, 130, 40, 40, 100).unwrap(), 130);
+        assert!(calc_twap(i128::MAX, 1, 0, 0, 100).is_err());
+    }
+
+    #[test]
+    fn test_get_contracts_amount() {
+        assert_eq!(get_contracts_amount(1_000, 1, 10).unwrap(), 100);
+        assert_eq!(get_contracts_amount(1_000, 3, 10).unwrap(), 300);
+        assert_eq!(get_contracts_amount(1_000, 10, 10).unwrap(), 1_000);
+        assert_eq!(get_contracts_amount(3_500, 1, 1).unwrap(), 3_500);
+        assert!(get_contracts_amount(u64::MAX, 2, 1).is_err());
     }
 
     #[test]
@@ -42,8 +198,27 @@ mod tests {
         assert_eq!(get_settlements(130, 140, 1_000), [928, 72]); // 928.57, 71.42
 
         assert_eq!(
-            get_settlements(calc_strike(3000000000, 450000000), 15180059921, 3000000000),
+            get_settlements(
+                calc_strike(3000000000, 450000000, 9, 6, -8).unwrap(),
+                15180059921,
+                3000000000
+            ),
             [2964415175, 35584825]
         );
     }
+
+    #[test]
+    fn test_get_put_settlements() {
+        // Can handle if it is out of the money
+        assert_eq!(get_put_settlements(130, 140, 1_000), [1_000, 0]);
+        // Can handle if it is at the money
+        assert_eq!(get_put_settlements(130, 130, 1_000), [1_000, 0]);
+
+        // In the money
+        assert_eq!(get_put_settlements(130, 120, 1_000), [923, 77]); // 923.07, 76.92
+
+        // Can handle a non-positive mark
+        assert_eq!(get_put_settlements(130, 0, 1_000), [0, 1_000]);
+        assert_eq!(get_put_settlements(130, -5, 1_000), [0, 1_000]);
+    }
 }
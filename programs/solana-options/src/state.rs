@@ -1,10 +1,44 @@
 use anchor_lang::prelude::*;
 
+use crate::error::ErrorCode;
+use crate::math::{calc_conf_bps, calc_twap};
+use crate::{MARK_CHALLENGE_PERIOD, MAX_ALLOWED_FEEDS, MIN_TWAP_SAMPLES, SETTLEMENT_GRACE_PERIOD};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OptionKind {
+    Call,
+    Put,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum SettlementType {
+    // Buyer receives the in the money portion of base
+    Cash,
+    // Buyer pays amount_quote and receives all of base
+    Physical,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum ExerciseStyle {
+    // Exercisable only after expiry against the marked price
+    European,
+    // Also exercisable before expiry against a fresh oracle price
+    American,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum PriceSource {
+    // The last mark before expiry
+    Mark,
+    // The time weighted average of the marks sampled before expiry
+    Twap,
+}
+
 #[account]
 #[derive(InitSpace)]
 pub struct CoveredCall {
     pub seller: Pubkey,
-    pub buyer: Pubkey,
+    pub buyer: Option<Pubkey>,
     pub amount_base: u64,
     pub amount_quote: u64,
     pub timestamp_expiry: i64,
@@ -14,6 +48,23 @@ pub struct CoveredCall {
     pub amount_premium: Option<u64>,
     pub is_exercised: bool,
     pub timestamp_created: i64,
+    pub feed_id: [u8; 32],
+    pub mint_premium: Pubkey,
+    pub amount_premium_ask: u64,
+    pub is_premium_to_seller: bool,
+    pub mint_option: Option<Pubkey>,
+    pub decimals_base: u8,
+    pub decimals_quote: u8,
+    pub settlement: SettlementType,
+    pub exercise_style: ExerciseStyle,
+    // Position size in contracts, each covering an equal share of base and quote
+    pub contracts: u64,
+    pub contracts_exercised: u64,
+    // Widest confidence interval, in basis points of the price, accepted at exercise
+    pub max_conf_bps: u16,
+    // Seconds before expiry the mark must be published in to settle this option
+    pub mark_window: i64,
+    pub price_source: PriceSource,
 }
 
 #[account]
@@ -25,4 +76,190 @@ pub struct ExpiryData {
     pub publish_time: i64,
     pub bump: u8,
     pub payer: Pubkey,
+    pub feed_id: [u8; 32],
+    // Sum of each sampled price times the seconds until the next sample, the last
+    // sample is the mark itself and is weighted up to expiry when settling
+    pub twap_sum: i128,
+    pub twap_count: u32,
+    pub twap_first_publish_time: i64,
+    // Bought options and series that have yet to settle against this mark
+    pub ref_count: u64,
+}
+
+impl ExpiryData {
+    // Registers a bought option, creating the account with its payer on first use
+    pub fn retain(&mut self, payer: Pubkey, feed_id: [u8; 32], bump: u8) -> Result<()> {
+        if self.payer == Pubkey::default() {
+            self.payer = payer;
+            self.feed_id = feed_id;
+            self.bump = bump;
+        }
+        self.ref_count = self
+            .ref_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+
+    // Called once an option no longer needs the mark to settle
+    pub fn release(&mut self) {
+        self.ref_count = self.ref_count.saturating_sub(1);
+    }
+
+    // Whether the mark was published within `window` seconds before expiry
+    pub fn is_within_window(&self, timestamp_expiry: i64, window: i64) -> bool {
+        timestamp_expiry - window < self.publish_time
+    }
+
+    // Time weighted average price up to expiry, once enough samples are recorded
+    pub fn twap(&self, timestamp_expiry: i64) -> Result<i64> {
+        require!(self.twap_count >= MIN_TWAP_SAMPLES, ErrorCode::TwapNotReady);
+        calc_twap(
+            self.twap_sum,
+            self.price,
+            self.twap_first_publish_time,
+            self.publish_time,
+            timestamp_expiry,
+        )
+    }
+
+    // Marks may be improved until the challenge period after expiry has passed
+    pub fn is_final(timestamp_expiry: i64, now: i64) -> bool {
+        now >= timestamp_expiry.saturating_add(MARK_CHALLENGE_PERIOD)
+    }
+
+    // Without a price to settle at, options lapse once the grace period has passed, but
+    // not while marks are paused and nobody could have set one
+    pub fn can_lapse(timestamp_expiry: i64, now: i64, config: &Config) -> bool {
+        !config.is_mark_paused && now >= timestamp_expiry.saturating_add(SETTLEMENT_GRACE_PERIOD)
+    }
+
+    // Price an option settles at once final, rejecting marks outside its window or
+    // tolerance. The tolerance applies to the mark itself for both sources, TWAP
+    // samples were each held to the mark limit when they were recorded
+    pub fn settlement_price(
+        &self,
+        timestamp_expiry: i64,
+        mark_window: i64,
+        max_conf_bps: u16,
+        price_source: PriceSource,
+        now: i64,
+    ) -> Result<i64> {
+        // Incase expiry account was initialized but not set
+        require!(self.price != 0, ErrorCode::OptionNotMarked);
+        require!(
+            Self::is_final(timestamp_expiry, now),
+            ErrorCode::MarkNotFinal
+        );
+        require!(
+            self.is_within_window(timestamp_expiry, mark_window),
+            ErrorCode::PriceIrrelevant
+        );
+        require!(
+            calc_conf_bps(self.conf, self.price) <= u64::from(max_conf_bps),
+            ErrorCode::ConfidenceTooWide
+        );
+        match price_source {
+            PriceSource::Mark => Ok(self.price),
+            PriceSource::Twap => self.twap(timestamp_expiry),
+        }
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct CashSecuredPut {
+    pub seller: Pubkey,
+    pub buyer: Option<Pubkey>,
+    pub amount_base: u64,
+    pub amount_quote: u64,
+    pub timestamp_expiry: i64,
+    pub mint_quote: Pubkey,
+    pub mint_base: Pubkey,
+    pub bump: u8,
+    pub amount_premium: Option<u64>,
+    pub is_exercised: bool,
+    pub timestamp_created: i64,
+    pub feed_id: [u8; 32],
+    pub mint_premium: Pubkey,
+    pub amount_premium_ask: u64,
+    pub is_premium_to_seller: bool,
+    pub decimals_base: u8,
+    pub decimals_quote: u8,
+    pub max_conf_bps: u16,
+    pub mark_window: i64,
+    pub price_source: PriceSource,
+}
+
+// Pooled covered calls in one strike and expiry, written by many sellers
+#[account]
+#[derive(InitSpace)]
+pub struct OptionSeries {
+    // Base and quote covered by a single contract
+    pub amount_base: u64,
+    pub amount_quote: u64,
+    pub timestamp_expiry: i64,
+    pub mint_base: Pubkey,
+    pub mint_quote: Pubkey,
+    pub mint_option: Pubkey,
+    pub feed_id: [u8; 32],
+    pub exercise_style: ExerciseStyle,
+    pub decimals_base: u8,
+    pub decimals_quote: u8,
+    pub contracts_written: u64,
+    pub contracts_sold: u64,
+    pub contracts_exercised: u64,
+    // Base paid out of the vault to exercising holders
+    pub amount_base_paid: u64,
+    pub bump: u8,
+    // Settled by the grace period fallback, open contracts can no longer be exercised
+    pub is_lapsed: bool,
+    // Mark copied from the expiry on first settlement, zero until then
+    pub price_settled: i64,
+    pub exponent_settled: i32,
+    // Settlement parameters taken from the config when the series was created
+    pub max_conf_bps: u16,
+    pub mark_window: i64,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SeriesWriter {
+    pub series: Pubkey,
+    pub seller: Pubkey,
+    pub mint_premium: Pubkey,
+    // Premium asked per contract
+    pub amount_premium_ask: u64,
+    pub contracts_written: u64,
+    pub contracts_sold: u64,
+    pub bump: u8,
+}
+
+// Program wide parameters, set once after deploy and updated by the admin
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub admin: Pubkey,
+    // Proposed by the admin, takes over once it accepts
+    pub pending_admin: Option<Pubkey>,
+    // Protocol fees in basis points of the premium and of what the buyer is paid out
+    pub fee_bps_premium: u16,
+    pub fee_bps_settlement: u16,
+    // Mark window for options created without their own
+    pub mark_window: i64,
+    // Widest confidence interval, in basis points of the price, accepted for a mark
+    pub max_conf_bps: u16,
+    // Feeds options may be written on, any feed when empty
+    #[max_len(MAX_ALLOWED_FEEDS)]
+    pub allowed_feeds: Vec<[u8; 32]>,
+    pub is_write_paused: bool,
+    pub is_buy_paused: bool,
+    pub is_mark_paused: bool,
+    pub bump: u8,
+}
+
+impl Config {
+    pub fn is_feed_allowed(&self, feed_id: &[u8; 32]) -> bool {
+        self.allowed_feeds.is_empty() || self.allowed_feeds.contains(feed_id)
+    }
 }
@@ -15,29 +15,51 @@ expect.extend({
 export function getStrikePrice(
   amountBase: bigint,
   amountQuote: bigint,
+  decimalsBase = 9,
+  decimalsQuote = 6,
 ): number {
-  return Math.round(Number((amountQuote * 10n ** 3n) / amountBase));
+  return (
+    Number(amountQuote * 10n ** BigInt(decimalsBase)) /
+    Number(amountBase * 10n ** BigInt(decimalsQuote))
+  );
 }
 
-export function getQuoteAmountWithStrike(amountBase: bigint, strike: number) {
-  return BigInt(Math.round((Number(amountBase) * strike) / 10 ** 3));
+export function getQuoteAmountWithStrike(
+  amountBase: bigint,
+  strike: number,
+  decimalsBase = 9,
+  decimalsQuote = 6,
+) {
+  return BigInt(
+    Math.round(
+      (Number(amountBase) * strike * 10 ** decimalsQuote) / 10 ** decimalsBase,
+    ),
+  );
 }
 
-export function getPda(seeds: {
+type OptionSeeds = {
   amountBase: bigint;
   amountQuote: bigint;
-  buyer: PublicKey;
   expiry: bigint;
   mintBase: PublicKey;
   mintQuote: PublicKey;
   programId: PublicKey;
   seller: PublicKey;
-}) {
+};
+
+export function getPda(seeds: OptionSeeds) {
+  return getOptionPda("covered-call", seeds);
+}
+
+export function getPutPda(seeds: OptionSeeds) {
+  return getOptionPda("cash-secured-put", seeds);
+}
+
+function getOptionPda(prefix: string, seeds: OptionSeeds) {
   const [pda] = PublicKey.findProgramAddressSync(
     [
-      Buffer.from("covered-call"),
+      Buffer.from(prefix),
       seeds.seller.toBuffer(),
-      seeds.buyer.toBuffer(),
       seeds.mintBase.toBuffer(),
       seeds.mintQuote.toBuffer(),
       new BN(seeds.amountBase.toString()).toArrayLike(Buffer, "le", 8),
@@ -49,10 +71,19 @@ export function getPda(seeds: {
   return pda;
 }
 
-export function getExpiryPda(seeds: { expiry: Date; programId: PublicKey }) {
+export function getFeedId(hex: string): number[] {
+  return Array.from(Buffer.from(hex.replace(/^0x/, ""), "hex"));
+}
+
+export function getExpiryPda(seeds: {
+  expiry: Date;
+  feedId: number[];
+  programId: PublicKey;
+}) {
   const [pda] = PublicKey.findProgramAddressSync(
     [
       Buffer.from("expiry-meta"),
+      Buffer.from(seeds.feedId),
       new BN(Math.floor(seeds.expiry.getTime() / 1000).toString()).toArrayLike(
         Buffer,
         "le",
@@ -63,3 +94,45 @@ export function getExpiryPda(seeds: { expiry: Date; programId: PublicKey }) {
   );
   return pda;
 }
+
+export function getSeriesPda(seeds: {
+  amountBase: bigint;
+  amountQuote: bigint;
+  expiry: bigint;
+  feedId: number[];
+  isAmerican: boolean;
+  mintBase: PublicKey;
+  mintQuote: PublicKey;
+  programId: PublicKey;
+}) {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("option-series"),
+      seeds.mintBase.toBuffer(),
+      seeds.mintQuote.toBuffer(),
+      new BN(seeds.amountBase.toString()).toArrayLike(Buffer, "le", 8),
+      new BN(seeds.amountQuote.toString()).toArrayLike(Buffer, "le", 8),
+      new BN(seeds.expiry.toString()).toArrayLike(Buffer, "le", 8),
+      Buffer.from(seeds.feedId),
+      Buffer.from([seeds.isAmerican ? 1 : 0]),
+    ],
+    seeds.programId,
+  );
+  return pda;
+}
+
+export function getProgramDataPda(programId: PublicKey) {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [programId.toBuffer()],
+    new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111"),
+  );
+  return pda;
+}
+
+export function getConfigPda(programId: PublicKey) {
+  const [pda] = PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    programId,
+  );
+  return pda;
+}
@@ -15,6 +15,7 @@ import {
   createSyncNativeInstruction,
   getAssociatedTokenAddressSync,
   NATIVE_MINT,
+  TOKEN_PROGRAM_ID,
 } from "@solana/spl-token";
 import {
   Connection,
@@ -28,7 +29,7 @@ import {
 
 import { SolanaOptions } from "../target/types/solana_options";
 import IDL from "../target/idl/solana_options.json";
-import { getPda, getQuoteAmountWithStrike } from "./helpers";
+import { getFeedId, getPda, getQuoteAmountWithStrike } from "./helpers";
 import { parseUnits } from "./viem";
 import { PythSolanaReceiver } from "@pythnetwork/pyth-solana-receiver";
 
@@ -65,6 +66,10 @@ const program = new Program<SolanaOptions>(IDL as SolanaOptions, provider);
 
 const programId = new PublicKey(IDL.address);
 
+const solFeedId = getFeedId(
+  "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d"
+);
+
 // async function getProgramAccounts(program: Program<SolanaOptions>) {
 //   const accounts = await program.account.coveredCall.all();
 
@@ -132,7 +137,6 @@ describe.skip(
     const pda = getPda({
       amountQuote: amountQuote,
       amountBase: amountBase,
-      buyer: buyer.publicKey,
       expiry: BigInt(expiry.toString()),
       mintQuote: usdc,
       mintBase: NATIVE_MINT,
@@ -189,7 +193,16 @@ describe.skip(
         .initialize(
           new BN(amountBase.toString()),
           new BN(amountQuote.toString()),
-          expiry
+          expiry,
+          solFeedId,
+          new BN(amountPremium.toString()),
+          false,
+          { cash: {} },
+          { european: {} },
+          new BN(1),
+          100,
+          new BN(0),
+          { mark: {} }
         )
         .preInstructions([
           createAssociatedTokenAccountInstruction(
@@ -207,9 +220,11 @@ describe.skip(
         ])
         .accounts({
           buyer: buyer.publicKey,
+          mintPremium: NATIVE_MINT,
           mintQuote: usdc,
           mintBase: NATIVE_MINT,
           seller: seller.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
         })
         .postInstructions([
           createCloseAccountInstruction(
@@ -260,6 +275,8 @@ describe.skip(
           buyer: buyer.publicKey,
           payer: buyer.publicKey,
           mintPremium: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgramPremium: TOKEN_PROGRAM_ID,
         })
         .postInstructions([
           createCloseAccountInstruction(
@@ -299,7 +316,7 @@ describe.skip(
         "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
       const priceUpdate = pyth.getPriceFeedAccountAddress(0, SOL_PRICE_FEED_ID);
       const tx = await program.methods
-        .mark(expiry)
+        .mark(solFeedId, expiry)
         .accounts({
           payer: payer.publicKey,
           priceUpdate,
@@ -316,7 +333,7 @@ describe.skip(
       );
       console.log("Option is now expired, exercising");
       const tx = await program.methods
-        .exercise()
+        .exercise(new BN(1))
         .preInstructions([
           createAssociatedTokenAccountInstruction(
             buyer.publicKey,
@@ -330,6 +347,7 @@ describe.skip(
           mintQuote: usdc,
           data: pda,
           buyer: buyer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
         })
         .postInstructions([
           createCloseAccountInstruction(
@@ -374,7 +392,8 @@ describe.skip(
           data: pda,
           seller: seller.publicKey,
           payer: seller.publicKey,
-          buyer: buyer.publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenProgramPremium: TOKEN_PROGRAM_ID,
         })
         .postInstructions([
           createCloseAccountInstruction(
@@ -391,7 +410,7 @@ describe.skip(
 
     it("can close expiry account", async () => {
       const tx = await program.methods
-        .markClose(expiry)
+        .markClose(solFeedId, expiry)
         .signers([seller])
         .rpc();
 
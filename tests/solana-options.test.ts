@@ -18,25 +18,56 @@ import {
   createAssociatedTokenAccount,
   mintTo,
   getAccount,
+  transfer,
 } from "spl-token-bankrun";
-import { Keypair, LAMPORTS_PER_SOL, PublicKey, Signer } from "@solana/web3.js";
-import { getExpiryPda, getPda, getStrikePrice } from "./helpers.js";
+import {
+  Keypair,
+  LAMPORTS_PER_SOL,
+  PublicKey,
+  Signer,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import {
+  getConfigPda,
+  getExpiryPda,
+  getFeedId,
+  getPda,
+  getProgramDataPda,
+  getPutPda,
+  getSeriesPda,
+  getStrikePrice,
+} from "./helpers.js";
 import { getI32Codec, getI64Codec, getU64Codec } from "@solana/codecs-numbers";
 
 const authority = anchor.web3.Keypair.generate();
 const priceUpdate = new PublicKey(
   "7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE"
 );
+const solFeedId = getFeedId(
+  "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d"
+);
+const ethFeedId = getFeedId(
+  "0xff61491a931112ddf1bd8147cd1b641375f79f5825126d665480874634fd0ace"
+);
 async function getAtaTokenBalance(
   client: BanksClient,
   mint: PublicKey,
-  user: PublicKey
+  user: PublicKey,
+  programId = token.TOKEN_PROGRAM_ID
 ) {
-  const ata = token.getAssociatedTokenAddressSync(mint, user, true);
-
-  return await getAccount(client, ata)
-    .then((account) => account.amount)
-    .catch(() => BigInt(0));
+  const ata = token.getAssociatedTokenAddressSync(mint, user, true, programId);
+
+  const info = await client.getAccount(ata);
+  if (!info) {
+    return BigInt(0);
+  }
+  return token.unpackAccount(
+    ata,
+    { ...info, data: Buffer.from(info.data) },
+    programId
+  ).amount;
 }
 
 // From https://github.com/kevinheavey/solana-bankrun/issues/3#issuecomment-2211797870
@@ -62,26 +93,110 @@ async function fundAtaAccountWithPayer(
   mint: PublicKey,
   payer: Signer,
   user: PublicKey,
-  amount: bigint | number
+  amount: bigint | number,
+  programId = token.TOKEN_PROGRAM_ID
 ) {
-  const ata = await createAssociatedTokenAccount(client, payer, mint, user);
+  const ata = await createAssociatedTokenAccount(
+    client,
+    payer,
+    mint,
+    user,
+    programId
+  );
 
-  await mintTo(client, payer, mint, ata, authority, amount);
+  await mintTo(client, payer, mint, ata, authority, amount, [], programId);
 }
 
 async function fundAtaAccount(
   client: BanksClient,
   mint: PublicKey,
   signer: Signer,
-  amount: bigint | number
+  amount: bigint | number,
+  programId = token.TOKEN_PROGRAM_ID
 ) {
   return fundAtaAccountWithPayer(
     client,
     mint,
     signer,
     signer.publicKey,
-    amount
+    amount,
+    programId
+  );
+}
+
+// Token-2022 mint with `extensions`, set up by `getInstructions` first
+async function createMint2022(
+  context: ProgramTestContext,
+  decimals: number,
+  extensions: token.ExtensionType[],
+  getInstructions: (mint: PublicKey) => TransactionInstruction[]
+) {
+  const mint = Keypair.generate();
+  const space = token.getMintLen(extensions);
+  const rent = await context.banksClient.getRent();
+
+  const tx = new Transaction().add(
+    SystemProgram.createAccount({
+      fromPubkey: context.payer.publicKey,
+      newAccountPubkey: mint.publicKey,
+      space,
+      lamports: Number(rent.minimumBalance(BigInt(space))),
+      programId: token.TOKEN_2022_PROGRAM_ID,
+    }),
+    ...getInstructions(mint.publicKey),
+    token.createInitializeMintInstruction(
+      mint.publicKey,
+      decimals,
+      authority.publicKey,
+      null,
+      token.TOKEN_2022_PROGRAM_ID
+    )
+  );
+  tx.recentBlockhash = context.lastBlockhash;
+  tx.feePayer = context.payer.publicKey;
+  tx.sign(context.payer, mint);
+  await context.banksClient.processTransaction(tx);
+
+  return mint.publicKey;
+}
+
+// Sends the transaction built by `method` and decodes the events it emitted
+async function getEvents(
+  context: ProgramTestContext,
+  program: Program<SolanaOptions>,
+  method: { transaction: () => Promise<Transaction> },
+  signers: Signer[]
+) {
+  const tx = await method.transaction();
+  tx.recentBlockhash = context.lastBlockhash;
+  tx.feePayer = signers[0].publicKey;
+  tx.sign(...signers);
+  const { logMessages } = await context.banksClient.processTransaction(tx);
+
+  const parser = new anchor.EventParser(
+    program.programId,
+    new anchor.BorshCoder(program.idl)
   );
+  return Array.from(parser.parseLogs(logMessages));
+}
+
+// Bankrun deploys programs without program data, so write one for `authority`
+function setUpgradeAuthority(
+  context: ProgramTestContext,
+  programId: PublicKey,
+  authority: PublicKey
+) {
+  context.setAccount(getProgramDataPda(programId), {
+    data: Buffer.concat([
+      Buffer.from([3, 0, 0, 0]), // ProgramData variant
+      Buffer.alloc(8), // Slot
+      Buffer.from([1]), // Some
+      authority.toBuffer(),
+    ]),
+    owner: new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111"),
+    executable: false,
+    lamports: LAMPORTS_PER_SOL,
+  });
 }
 
 const warpTo = async (context: ProgramTestContext, ms: anchor.BN) => {
@@ -97,6 +212,9 @@ const warpTo = async (context: ProgramTestContext, ms: anchor.BN) => {
   );
 };
 
+// Marks are final, and can settle options, once this has passed after expiry
+const challengePeriod = 15 * 60;
+
 const fixtureDeployed = async () => {
   const context = await startAnchor(".", [], []);
   const payer = context.payer;
@@ -112,6 +230,16 @@ const fixtureDeployed = async () => {
   // @ts-ignore
   const program = new Program<SolanaOptions>(IDL, provider);
 
+  setUpgradeAuthority(context, program.programId, payer.publicKey);
+  await program.methods
+    .initializeConfig(payer.publicKey)
+    .accounts({
+      payer: payer.publicKey,
+      programData: getProgramDataPda(program.programId),
+    })
+    .signers([payer])
+    .rpc();
+
   const [wsol, usdc] = await Promise.all([
     createMint(
       context.banksClient,
@@ -134,14 +262,19 @@ const fixtureDeployed = async () => {
     fundAtaAccount(context.banksClient, wsol, buyer, BigInt(1000)),
   ]);
 
-  const setPrice = (price: number, time = new Date()) => {
+  const setPrice = (
+    price: number,
+    time = new Date(),
+    feedId = solFeedId,
+    conf = 12190053
+  ) => {
     const buff = Buffer.concat([
       Buffer.from([0x22, 0xf1, 0x23, 0x63, 0x9d, 0x7e, 0xf4, 0xcd]), // Discriminator
       new PublicKey("7UVimffxr9ow1uXYxsr4LHAcV58mLzhmwaeKvJ1pjLiE").toBuffer(), // Write Authority
       Buffer.from([0x01]), // Verification Level
-      Buffer.from("7w2Lb9os66QdoV1AldHaOSoNL47Qxse8D0z6yMKAtW0", "base64"), // Price Message - Fee ID
+      Buffer.from(feedId), // Price Message - Feed ID
       getI64Codec().encode(price * 10 ** 8), // Price Message - Price
-      getU64Codec().encode(12190053), // Price Message - Conf
+      getU64Codec().encode(conf), // Price Message - Conf
       getI32Codec().encode(-8), // Price Message - Exponent
       getI64Codec().encode(Math.floor(time.getTime() / 1000)), // Price Message - PublishTime
       getI64Codec().encode(Math.floor(time.getTime() / 1000)), // Price Message - Prev Publish Time
@@ -179,19 +312,29 @@ const fixtureInitialized = async () => {
     .initialize(
       new anchor.BN("1000"),
       new anchor.BN("3500"),
-      new anchor.BN(expiry)
+      new anchor.BN(expiry),
+      solFeedId,
+      new anchor.BN(10),
+      false,
+      { cash: {} },
+      { european: {} },
+      new anchor.BN(1),
+      200,
+      new anchor.BN(0),
+      { mark: {} }
     )
     .accounts({
       mintBase: wsol,
+      mintPremium: wsol,
       mintQuote: usdc,
       buyer: buyer.publicKey,
+      tokenProgram: token.TOKEN_PROGRAM_ID,
     })
     .rpc();
 
   const pda = getPda({
     amountQuote: 3500n,
     amountBase: 1000n,
-    buyer: buyer.publicKey,
     expiry: BigInt(expiry.toString()),
     mintQuote: usdc,
     mintBase: wsol,
@@ -217,6 +360,8 @@ const fixtureBought = async () => {
       buyer: buyer.publicKey,
       mintPremium: wsol,
       payer: buyer.publicKey,
+      tokenProgram: token.TOKEN_PROGRAM_ID,
+      tokenProgramPremium: token.TOKEN_PROGRAM_ID,
     })
     .signers([buyer])
     .rpc();
@@ -230,18 +375,19 @@ const fixtureExercised = async () => {
     fixture;
 
   setPrice(4000);
-  await program.methods.mark(expiry).accounts({ priceUpdate }).rpc();
-  await warpTo(context, expiry.add(new anchor.BN(10)));
+  await program.methods.mark(solFeedId, expiry).accounts({ priceUpdate }).rpc();
+  await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
 
   // Create and fund the ata account for the buyer
   await fundAtaAccount(context.banksClient, usdc, buyer, BigInt(3500));
   await program.methods
-    .exercise()
+    .exercise(new anchor.BN(1))
     .accounts({
       mintBase: wsol,
       mintQuote: usdc,
       data: pda,
       buyer: buyer.publicKey,
+      tokenProgram: token.TOKEN_PROGRAM_ID,
     })
     .signers([buyer])
     .rpc();
@@ -268,7 +414,6 @@ describe("solana-options", { timeout: 100_000 }, () => {
       const pda = getPda({
         amountQuote: 42n,
         amountBase: 1000n,
-        buyer: buyer.publicKey,
         expiry: BigInt(expiry.toString()),
         mintQuote: usdc,
         mintBase: wsol,
@@ -281,13 +426,24 @@ describe("solana-options", { timeout: 100_000 }, () => {
         .initialize(
           new anchor.BN("1000"),
           new anchor.BN("42"),
-          new anchor.BN(expiry)
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
         )
         .accounts({
           buyer: buyer.publicKey,
           mintQuote: usdc,
           mintBase: wsol,
+          mintPremium: wsol,
           seller: seller.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
           // data: pda,
         })
         .signers([seller])
@@ -297,13 +453,27 @@ describe("solana-options", { timeout: 100_000 }, () => {
       expect(await program.account.coveredCall.fetch(pda)).toStrictEqual({
         amountBase: expect.toBeBN(new anchor.BN(1000)),
         amountPremium: null,
+        amountPremiumAsk: expect.toBeBN(new anchor.BN(10)),
         amountQuote: expect.toBeBN(new anchor.BN(42)),
         bump: expect.any(Number),
         buyer: buyer.publicKey,
+        contracts: expect.toBeBN(new anchor.BN(1)),
+        contractsExercised: expect.toBeBN(new anchor.BN(0)),
+        decimalsBase: 9,
+        decimalsQuote: 6,
+        exerciseStyle: { european: {} },
+        feedId: solFeedId,
         isExercised: false,
+        isPremiumToSeller: false,
+        markWindow: expect.toBeBN(new anchor.BN(1800)),
+        maxConfBps: 200,
         mintBase: wsol,
+        mintOption: null,
+        mintPremium: wsol,
         mintQuote: usdc,
+        priceSource: { mark: {} },
         seller: seller.publicKey,
+        settlement: { cash: {} },
         timestampExpiry: expect.toBeBN(expiry),
         timestampCreated: expect.any(BN),
       });
@@ -329,12 +499,23 @@ describe("solana-options", { timeout: 100_000 }, () => {
         .initialize(
           new anchor.BN("500"),
           new anchor.BN(1),
-          new anchor.BN(expiry)
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
         )
         .accounts({
           mintBase: wsol,
+          mintPremium: wsol,
           mintQuote: usdc,
           buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
         })
         .rpc();
 
@@ -342,12 +523,23 @@ describe("solana-options", { timeout: 100_000 }, () => {
         .initialize(
           new anchor.BN("500"),
           new anchor.BN(1),
-          new anchor.BN(expiry)
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
         )
         .accounts({
           mintBase: wsol,
+          mintPremium: wsol,
           mintQuote: usdc,
           buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
         })
         .rpc();
     });
@@ -365,18 +557,132 @@ describe("solana-options", { timeout: 100_000 }, () => {
           .initialize(
             new anchor.BN("1000"),
             new anchor.BN(1),
-            new anchor.BN(Math.floor(Date.now() / 1000) - 600)
+            new anchor.BN(Math.floor(Date.now() / 1000) - 600),
+            solFeedId,
+            new anchor.BN(10),
+            false,
+            { cash: {} },
+            { european: {} },
+            new anchor.BN(1),
+            200,
+            new anchor.BN(0),
+            { mark: {} }
           )
           .accounts({
             mintBase: wsol,
+            mintPremium: wsol,
             mintQuote: usdc,
             buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
           })
           .rpc()
       ).rejects.toThrowError(
         /^AnchorError thrown in programs\/solana-options\/src\/instructions\/initialize.rs:\d+. Error Code: ExpiryIsInThePast. Error Number: 6000. Error Message: Expiry is in the past.$/
       );
     });
+
+    it("Can reject a confidence tolerance no mark can meet", async () => {
+      const { program, wsol, usdc, buyer } = await fixtureDeployed();
+
+      for (const maxConfBps of [0, 1001]) {
+        await expect(
+          program.methods
+            .initialize(
+              new anchor.BN("1000"),
+              new anchor.BN("3500"),
+              new anchor.BN(Math.floor(Date.now() / 1000) + 180),
+              solFeedId,
+              new anchor.BN(10),
+              false,
+              { cash: {} },
+              { european: {} },
+              new anchor.BN(1),
+              maxConfBps,
+              new anchor.BN(0),
+              { mark: {} }
+            )
+            .accounts({
+              mintBase: wsol,
+              mintPremium: wsol,
+              mintQuote: usdc,
+              buyer: buyer.publicKey,
+              tokenProgram: token.TOKEN_PROGRAM_ID,
+            })
+            .rpc()
+        ).rejects.toThrowError(
+          /AnchorError thrown in programs\/solana-options\/src\/instructions\/initialize.rs:\d+. Error Code: InvalidConfTolerance. Error Number: 6036. Error Message: Confidence tolerance is out of range./
+        );
+      }
+    });
+
+    it("Can reject a zero amount", async () => {
+      const { program, wsol, usdc, buyer } = await fixtureDeployed();
+
+      for (const [amountBase, amountQuote] of [
+        [0, 3500],
+        [1000, 0],
+      ]) {
+        await expect(
+          program.methods
+            .initialize(
+              new anchor.BN(amountBase),
+              new anchor.BN(amountQuote),
+              new anchor.BN(Math.floor(Date.now() / 1000) + 180),
+              solFeedId,
+              new anchor.BN(10),
+              false,
+              { cash: {} },
+              { european: {} },
+              new anchor.BN(1),
+              200,
+              new anchor.BN(0),
+              { mark: {} }
+            )
+            .accounts({
+              mintBase: wsol,
+              mintPremium: wsol,
+              mintQuote: usdc,
+              buyer: buyer.publicKey,
+              tokenProgram: token.TOKEN_PROGRAM_ID,
+            })
+            .rpc()
+        ).rejects.toThrowError(
+          /AnchorError thrown in programs\/solana-options\/src\/instructions\/initialize.rs:\d+. Error Code: ZeroAmount. Error Number: 6038. Error Message: Amount must be greater than zero./
+        );
+      }
+    });
+    it("Can reject a confidence tolerance tighter than the config", async () => {
+      const { program, wsol, usdc, buyer } = await fixtureDeployed();
+
+      // The config accepts marks up to 200 bps
+      await expect(
+        program.methods
+          .initialize(
+            new anchor.BN("1000"),
+            new anchor.BN("3500"),
+            new anchor.BN(Math.floor(Date.now() / 1000) + 180),
+            solFeedId,
+            new anchor.BN(10),
+            false,
+            { cash: {} },
+            { european: {} },
+            new anchor.BN(1),
+            199,
+            new anchor.BN(0),
+            { mark: {} }
+          )
+          .accounts({
+            mintBase: wsol,
+            mintPremium: wsol,
+            mintQuote: usdc,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/initialize.rs:\d+. Error Code: InvalidConfTolerance. Error Number: 6036. Error Message: Confidence tolerance is out of range./
+      );
+    });
     it("Can reject initialize with insufficient base", async () => {
       const { context, program, wsol, seller, usdc, buyer } =
         await fixtureDeployed();
@@ -390,12 +696,23 @@ describe("solana-options", { timeout: 100_000 }, () => {
           .initialize(
             new anchor.BN("10000"),
             new anchor.BN(1),
-            new anchor.BN(Math.floor(Date.now() / 1000) + 60)
+            new anchor.BN(Math.floor(Date.now() / 1000) + 60),
+            solFeedId,
+            new anchor.BN(10),
+            false,
+            { cash: {} },
+            { european: {} },
+            new anchor.BN(1),
+            200,
+            new anchor.BN(0),
+            { mark: {} }
           )
           .accounts({
             mintBase: wsol,
+            mintPremium: wsol,
             mintQuote: usdc,
             buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
           })
           .rpc()
       ).rejects.toThrowError(
@@ -420,6 +737,8 @@ describe("solana-options", { timeout: 100_000 }, () => {
           buyer: buyer.publicKey,
           mintPremium: wsol,
           payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
         })
         .signers([buyer])
         .rpc();
@@ -428,13 +747,27 @@ describe("solana-options", { timeout: 100_000 }, () => {
       expect(await program.account.coveredCall.fetch(pda)).toStrictEqual({
         amountBase: expect.toBeBN(new BN(1000)),
         amountPremium: expect.toBeBN(new BN(10)),
+        amountPremiumAsk: expect.toBeBN(new BN(10)),
         amountQuote: expect.toBeBN(new BN(3500)),
         bump: expect.any(Number),
         buyer: buyer.publicKey,
+        contracts: expect.toBeBN(new anchor.BN(1)),
+        contractsExercised: expect.toBeBN(new anchor.BN(0)),
+        decimalsBase: 9,
+        decimalsQuote: 6,
+        exerciseStyle: { european: {} },
+        feedId: solFeedId,
         isExercised: false,
+        isPremiumToSeller: false,
+        markWindow: expect.toBeBN(new anchor.BN(1800)),
+        maxConfBps: 200,
         mintBase: wsol,
+        mintOption: null,
+        mintPremium: wsol,
         mintQuote: usdc,
+        priceSource: { mark: {} },
         seller: seller.publicKey,
+        settlement: { cash: {} },
         timestampCreated: expect.any(BN),
         timestampExpiry: expect.toBeBN(expiry),
       });
@@ -448,6 +781,136 @@ describe("solana-options", { timeout: 100_000 }, () => {
       );
     });
 
+    it("Can allow anyone to buy an open option", async () => {
+      const { context, program, wsol, usdc, seller } = await fixtureDeployed();
+
+      const keeper = Keypair.generate();
+      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
+      await fundAtaAccount(context.banksClient, wsol, keeper, BigInt(1000));
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: null,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+      expect((await program.account.coveredCall.fetch(pda)).buyer).toBeNull();
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: keeper.publicKey,
+          mintPremium: wsol,
+          payer: keeper.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc();
+
+      expect((await program.account.coveredCall.fetch(pda)).buyer).toEqual(
+        keeper.publicKey
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, keeper.publicKey)
+      ).to.equal(BigInt(990));
+    });
+
+    it("Can pay premium directly to seller", async () => {
+      const { context, program, wsol, usdc, seller, buyer } =
+        await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          true,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          ataVaultPremium: null,
+          ataSellerPremium: token.getAssociatedTokenAddressSync(
+            wsol,
+            seller.publicKey
+          ),
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(10));
+      // Vault only holds collateral
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(1000)
+      );
+    });
+
     it("Can allow 3rd party to successfully buy for buyer", async () => {
       const { program, pda, buyer, wsol, context, expiry, usdc, seller } =
         await fixtureInitialized();
@@ -462,6 +925,8 @@ describe("solana-options", { timeout: 100_000 }, () => {
             buyer: buyer.publicKey,
             mintPremium: wsol,
             payer: keeper.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
           })
           .signers([keeper])
           .rpc();
@@ -470,13 +935,27 @@ describe("solana-options", { timeout: 100_000 }, () => {
       expect(await program.account.coveredCall.fetch(pda)).toStrictEqual({
         amountBase: expect.toBeBN(new BN(1000)),
         amountPremium: expect.toBeBN(new BN(10)),
+        amountPremiumAsk: expect.toBeBN(new BN(10)),
         amountQuote: expect.toBeBN(new BN(3500)),
         bump: expect.any(Number),
         buyer: buyer.publicKey,
+        contracts: expect.toBeBN(new anchor.BN(1)),
+        contractsExercised: expect.toBeBN(new anchor.BN(0)),
+        decimalsBase: 9,
+        decimalsQuote: 6,
+        exerciseStyle: { european: {} },
+        feedId: solFeedId,
         isExercised: false,
+        isPremiumToSeller: false,
+        markWindow: expect.toBeBN(new anchor.BN(1800)),
+        maxConfBps: 200,
         mintBase: wsol,
+        mintOption: null,
+        mintPremium: wsol,
         mintQuote: usdc,
+        priceSource: { mark: {} },
         seller: seller.publicKey,
+        settlement: { cash: {} },
         timestampCreated: expect.any(BN),
         timestampExpiry: expect.toBeBN(expiry),
       });
@@ -501,6 +980,8 @@ describe("solana-options", { timeout: 100_000 }, () => {
           buyer: buyer.publicKey,
           mintPremium: wsol,
           payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
         })
         .signers([buyer])
         .rpc();
@@ -516,11 +997,13 @@ describe("solana-options", { timeout: 100_000 }, () => {
             data: pda,
             mintPremium: wsol,
             payer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/buy.rs:\d\d. Error Code: OptionAlreadyBought. Error Number: 6002. Error Message: Option is already bought./
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/buy.rs:\d+. Error Code: OptionAlreadyBought. Error Number: 6002. Error Message: Option is already bought./
       );
     });
 
@@ -539,17 +1022,18 @@ describe("solana-options", { timeout: 100_000 }, () => {
             data: pda,
             buyer: buyer.publicKey,
             mintPremium: wsol,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/buy.rs:\d\d. Error Code: OptionExpired. Error Number: 6001. Error Message: Option has expired./
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/buy.rs:\d+. Error Code: OptionExpired. Error Number: 6001. Error Message: Option has expired./
       );
     });
 
-    it("Can reject buy if premium is not in base", async () => {
-      const { program, pda, seller, buyer, wsol, context, usdc } =
-        await fixtureInitialized();
+    it("Can reject buy if premium is not in the premium mint", async () => {
+      const { program, pda, buyer, context, usdc } = await fixtureInitialized();
 
       await fundAtaAccount(context.banksClient, usdc, buyer, BigInt(500));
 
@@ -565,29 +1049,130 @@ describe("solana-options", { timeout: 100_000 }, () => {
             data: pda,
             buyer: buyer.publicKey,
             mintPremium: usdc,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        "AnchorError caused by account: ata_vault_premium. Error Code: AccountNotInitialized. Error Number: 3012. Error Message: The program expected this account to be already initialized."
+        "AnchorError caused by account: mint_premium. Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated."
+      );
+    });
+
+    it("Can buy with premium in quote", async () => {
+      const { context, program, wsol, usdc, seller, buyer, setPrice } =
+        await fixtureDeployed();
+      await fundAtaAccount(context.banksClient, usdc, buyer, BigInt(500));
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(100),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: usdc,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(100))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: usdc,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, buyer.publicKey)
+      ).to.equal(BigInt(400));
+      expect(await getAtaTokenBalance(context.banksClient, usdc, pda)).to.equal(
+        BigInt(100)
+      );
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(1000)
       );
 
-      // Also test when ata is initialized
-      await createAssociatedTokenAccount(context.banksClient, buyer, usdc, pda);
+      // Premium is routed to the seller on close
+      await fundAtaAccount(context.banksClient, usdc, seller, 0);
+      await warpTo(context, expiry.add(new anchor.BN(100)));
+      setPrice(3000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await program.methods
+        .close()
+        .accounts({
+          mintBase: wsol,
+          mintPremium: usdc,
+          data: pda,
+          seller: seller.publicKey,
+          payer: seller.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, seller.publicKey)
+      ).to.equal(BigInt(100));
+      expect(
+        await context.banksClient.getAccount(
+          token.getAssociatedTokenAddressSync(usdc, pda, true)
+        )
+      ).to.equal(null);
+    });
+
+    it("Can reject if premium is below asking premium", async () => {
+      const { program, pda, buyer, wsol } = await fixtureInitialized();
 
       await expect(
         program.methods
-          .buy(new anchor.BN(500))
+          .buy(new anchor.BN(9))
           .accounts({
             payer: buyer.publicKey,
             data: pda,
             buyer: buyer.publicKey,
-            mintPremium: usdc,
+            mintPremium: wsol,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        "AnchorError caused by account: mint_premium. Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated."
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/buy.rs:\d+. Error Code: PremiumTooLow. Error Number: 6009. Error Message: Premium is below the asking premium./
       );
     });
 
@@ -605,6 +1190,8 @@ describe("solana-options", { timeout: 100_000 }, () => {
             data: pda,
             buyer: buyer.publicKey,
             mintPremium: wsol,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
@@ -624,6 +1211,8 @@ describe("solana-options", { timeout: 100_000 }, () => {
             data: pda,
             buyer: seller.publicKey,
             mintPremium: wsol,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
           })
           .signers([seller])
           .rpc()
@@ -635,22 +1224,25 @@ describe("solana-options", { timeout: 100_000 }, () => {
 
   describe("Exercise instruction", () => {
     it("Can reject if option has not been marked", async () => {
-      const { program, pda, buyer, wsol, context, usdc } =
+      const { program, pda, buyer, wsol, context, usdc, expiry } =
         await fixtureBought();
 
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
       await expect(
         program.methods
-          .exercise()
+          .exercise(new anchor.BN(1))
           .accounts({
             mintBase: wsol,
             mintQuote: usdc,
             data: pda,
             buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        "AnchorError caused by account: expiry. Error Code: AccountNotInitialized. Error Number: 3012. Error Message: The program expected this account to be already initialized."
+        /AnchorError thrown in programs\/solana-options\/src\/state.rs:\d+. Error Code: OptionNotMarked. Error Number: 6008. Error Message: Option not marked./
       );
     });
 
@@ -658,7 +1250,10 @@ describe("solana-options", { timeout: 100_000 }, () => {
       const fixture = await fixtureBought();
       const { program, setPrice, expiry } = fixture;
       setPrice(4000);
-      await program.methods.mark(expiry).accounts({ priceUpdate }).rpc();
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
       return fixture;
     };
 
@@ -671,15 +1266,19 @@ describe("solana-options", { timeout: 100_000 }, () => {
       ).to.equal(BigInt(990));
 
       expect(getStrikePrice(1000n, 3500n)).to.equal(3500);
+      expect(getStrikePrice(100_000_000n, 3_500_000_000n, 8, 6)).to.equal(
+        3500
+      );
 
-      await warpTo(context, expiry.add(new anchor.BN(100)));
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
       await program.methods
-        .exercise()
+        .exercise(new anchor.BN(1))
         .accounts({
           mintBase: wsol,
           mintQuote: usdc,
           data: pda,
           buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
         })
         .signers([buyer])
         .rpc();
@@ -692,491 +1291,3730 @@ describe("solana-options", { timeout: 100_000 }, () => {
       );
     });
 
-    it("Can reject if option has already been exercised", async () => {
-      const { program, pda, buyer, wsol, context, usdc, expiry } =
-        await fixtureMarked();
+    it("Can reject if expiry was marked on another feed", async () => {
+      const { program, pda, buyer, wsol, context, usdc, setPrice, expiry } =
+        await fixtureBought();
 
-      await warpTo(context, expiry.add(new anchor.BN(100)));
+      setPrice(4000, new Date(), ethFeedId);
       await program.methods
-        .exercise()
-        .accounts({
-          mintBase: wsol,
-          mintQuote: usdc,
-          data: pda,
-          buyer: buyer.publicKey,
-        })
-        .signers([buyer])
+        .mark(ethFeedId, expiry)
+        .accounts({ priceUpdate })
         .rpc();
-
-      // Wait to avoid getting the error "This transaction has already been processed"
-      await new Promise((resolve) => setTimeout(resolve, 1));
+      await warpTo(context, expiry.add(new anchor.BN(100)));
 
       await expect(
         program.methods
-          .exercise()
+          .exercise(new anchor.BN(1))
           .accounts({
             mintBase: wsol,
             mintQuote: usdc,
             data: pda,
             buyer: buyer.publicKey,
+            expiry: getExpiryPda({
+              expiry: new Date(expiry.toNumber() * 1000),
+              feedId: ethFeedId,
+              programId: program.programId,
+            }),
+            tokenProgram: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d\d. Error Code: OptionAlreadyExercised. Error Number: 6005. Error Message: Option already exercised./
+        "AnchorError caused by account: expiry. Error Code: ConstraintSeeds. Error Number: 2006. Error Message: A seeds constraint was violated."
       );
     });
 
-    it("Can reject if option hasn't been bought", async () => {
-      const { program, pda, buyer, wsol, context, usdc, setPrice, expiry } =
-        await fixtureInitialized();
-
-      setPrice(4000);
-      await program.methods.mark(expiry).accounts({ priceUpdate }).rpc();
-
-      await warpTo(context, expiry.add(new anchor.BN(100)));
+    it("Can reject if mark confidence exceeds the option tolerance", async () => {
+      const { program, buyer, wsol, usdc, context, seller, payer, setPrice } =
+        await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // Widening the config after the option is written admits marks it rejects
+      await program.methods
+        .updateConfig({
+          feeBpsPremium: 0,
+          feeBpsSettlement: 0,
+          markWindow: new anchor.BN(30 * 60),
+          maxConfBps: 400,
+          allowedFeeds: [],
+          isWritePaused: false,
+          isBuyPaused: false,
+          isMarkPaused: false,
+        })
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      // 300 bps is accepted as a mark but not by this option
+      setPrice(4000, new Date(), solFeedId, 12000000000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
 
       await expect(
         program.methods
-          .exercise()
+          .exercise(new anchor.BN(1))
           .accounts({
             mintBase: wsol,
             mintQuote: usdc,
             data: pda,
             buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d\d. Error Code: OptionNotPurchased. Error Number: 6003. Error Message: Option was not purchased./
+        /AnchorError thrown in programs\/solana-options\/src\/state.rs:\d+. Error Code: ConfidenceTooWide. Error Number: 6021. Error Message: Price confidence interval is too wide./
       );
     });
 
-    it("Can reject if not buyer", async () => {
-      const { program, pda, seller, wsol, usdc, context } =
-        await fixtureMarked();
+    it("Can reject if mark is older than the option mark window", async () => {
+      const { program, buyer, wsol, usdc, context, seller, setPrice } =
+        await fixtureDeployed();
 
-      await expect(
-        program.methods
-          .exercise()
-          .accounts({
-            mintBase: wsol,
-            mintQuote: usdc,
-            data: pda,
-            buyer: seller.publicKey,
-          })
-          .signers([seller])
-          .rpc()
-      ).rejects.toThrowError(
-        "AnchorError caused by account: buyer. Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated."
-      );
-    });
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(60),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
 
-    it("Can reject if option as not expired", async () => {
-      const { program, pda, buyer, wsol, context, usdc, expiry } =
-        await fixtureMarked();
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // A mark 3 minutes before expiry is outside this option's 1 minute window
+      setPrice(4000, new Date(), solFeedId);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
 
       await expect(
         program.methods
-          .exercise()
+          .exercise(new anchor.BN(1))
           .accounts({
             mintBase: wsol,
             mintQuote: usdc,
             data: pda,
             buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
           })
           .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d\d. Error Code: OptionNotExpired. Error Number: 6006. Error Message: Option has not expired./
+        /AnchorError thrown in programs\/solana-options\/src\/state.rs:\d+. Error Code: PriceIrrelevant. Error Number: 6007. Error Message: Price not close to expiry./
       );
     });
-  });
-
-  describe("Close instruction", () => {
-    it("Can successfully close exercised option by seller", async () => {
-      const { program, pda, buyer, wsol, context, usdc, seller } =
-        await fixtureExercised();
 
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(885)
-      );
-      expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(0));
+    it("Can exercise against the TWAP of the marks", async () => {
+      const { program, buyer, wsol, usdc, context, seller, setPrice } =
+        await fixtureDeployed();
 
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
       await program.methods
-        .close()
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { twap: {} }
+        )
         .accounts({
           mintBase: wsol,
-          data: pda,
-          seller: seller.publicKey,
+          mintPremium: wsol,
+          mintQuote: usdc,
           buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
         })
-        .signers([seller])
         .rpc();
 
-      // Expect accounts to be close
-      expect(await context.banksClient.getAccount(pda)).to.equal(null);
-      expect(
-        await context.banksClient.getAccount(
-          token.getAssociatedTokenAddressSync(wsol, pda, true)
-        )
-      ).to.equal(null);
-      expect(
-        await context.banksClient.getAccount(
-          token.getAssociatedTokenAddressSync(usdc, pda, true)
-        )
-      ).to.equal(null);
-
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(0)
-      );
-      expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(885));
-    });
-
-    it("Can successfully close exercised option by anyone", async () => {
-      const { program, pda, buyer, wsol, context, usdc, seller, payer } =
-        await fixtureExercised();
-
-      const keeper = Keypair.generate();
-      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
 
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(885)
-      );
-      expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(0));
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
 
-      await fundAtaAccount(context.banksClient, usdc, seller, 0);
+      // 3000 for 60s, 4000 for 30s and 5000 for the last 30s averages 3750
+      setPrice(3000, new Date((expiry.toNumber() - 120) * 1000));
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      setPrice(4000, new Date((expiry.toNumber() - 60) * 1000));
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      setPrice(5000, new Date((expiry.toNumber() - 30) * 1000));
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
 
       await program.methods
-        .close()
+        .exercise(new anchor.BN(1))
         .accounts({
           mintBase: wsol,
           mintQuote: usdc,
           data: pda,
-          seller: seller.publicKey,
-          payer: keeper.publicKey,
           buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
         })
-        .signers([keeper])
+        .signers([buyer])
         .rpc();
 
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(0)
-      );
+      // Settled at 3750 rather than the last mark of 5000
       expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(885));
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(990 + 67));
     });
 
-    it("Can successfully close unbought option by anyone", async () => {
-      const { program, pda, buyer, wsol, context, usdc, seller } =
-        await fixtureInitialized();
-
-      const keeper = Keypair.generate();
-      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
-
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(1000)
-      );
-      expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(0));
+    it("Can reject if the TWAP has too few samples", async () => {
+      const { program, buyer, wsol, usdc, context, seller, setPrice } =
+        await fixtureDeployed();
 
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
       await program.methods
-        .close()
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { twap: {} }
+        )
         .accounts({
           mintBase: wsol,
-          data: pda,
-          seller: seller.publicKey,
-          payer: keeper.publicKey,
+          mintPremium: wsol,
+          mintQuote: usdc,
           buyer: buyer.publicKey,
-          expiry: null,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
         })
-        .signers([keeper])
         .rpc();
 
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(0)
-      );
-      expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(1000));
-    });
-
-    it("Can successfully close unexercised option after expiry", async () => {
-      const { program, pda, wsol, context, seller, expiry, buyer, setPrice } =
-        await fixtureBought();
-
-      await warpTo(context, expiry.add(new anchor.BN(100)));
-
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(1010)
-      );
-      expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(0));
-
-      setPrice(3000);
-      await program.methods.mark(expiry).accounts({ priceUpdate }).rpc();
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
 
       await program.methods
-        .close()
+        .buy(new anchor.BN(10))
         .accounts({
-          mintBase: wsol,
           data: pda,
-          seller: seller.publicKey,
-          payer: seller.publicKey,
           buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
         })
-        .signers([seller])
+        .signers([buyer])
         .rpc();
 
-      // Expect accounts to be close
-      expect(await context.banksClient.getAccount(pda)).to.equal(null);
-      expect(
-        await context.banksClient.getAccount(
-          token.getAssociatedTokenAddressSync(wsol, pda, true)
-        )
-      ).to.equal(null);
-
-      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
-        BigInt(0)
-      );
-      expect(
-        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
-      ).to.equal(BigInt(1010));
-    });
-
-    it("Can reject closing bought option before expiry", async () => {
-      const {
-        program,
-        pda,
-        wsol,
-        context,
-        usdc,
-        seller,
-        buyer,
-        expiry,
-        setPrice,
-      } = await fixtureBought();
+      setPrice(3000, new Date((expiry.toNumber() - 120) * 1000));
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      setPrice(4000, new Date((expiry.toNumber() - 60) * 1000));
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
 
       await expect(
         program.methods
-          .close()
+          .exercise(new anchor.BN(1))
           .accounts({
-            buyer: buyer.publicKey,
-            data: pda,
-            mintQuote: usdc,
             mintBase: wsol,
-            payer: seller.publicKey,
-            seller: seller.publicKey,
-            expiry: null,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
           })
-          .signers([seller])
+          .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/close.rs:\d\d. Error Code: OptionCannotBeClosedYet. Error Number: 6004. Error Message: Option cannot be closed Yet./
+        /AnchorError thrown in programs\/solana-options\/src\/state.rs:\d+. Error Code: TwapNotReady. Error Number: 6023. Error Message: Not enough price samples for the TWAP./
       );
     });
-  });
 
-  it("has correct price update account", async () => {
-    const { provider } = await fixtureDeployed();
-
-    const SOL_PRICE_FEED_ID =
-      "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
-
-    const pyth = new PythSolanaReceiver(provider);
-    expect(pyth.getPriceFeedAccountAddress(0, SOL_PRICE_FEED_ID)).to.deep.equal(
-      priceUpdate
-    );
-  });
-
-  describe("Can set mark price", () => {
-    it("Can reject if mark price is no close enough to expiry", async () => {
-      const { program, context, provider, setPrice } = await fixtureDeployed();
+    it("Can reject exercise while the mark can still be challenged", async () => {
+      const { program, pda, buyer, wsol, context, usdc, expiry } =
+        await fixtureMarked();
 
-      const expiry = new Date();
-      const publishTime = new Date(expiry.getTime() - 30 * 60 * 1000 - 1);
-      setPrice(130, publishTime);
+      await warpTo(context, expiry.add(new anchor.BN(100)));
 
       await expect(
         program.methods
-          .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
-          .accounts({ priceUpdate })
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d\d. Error Code: PriceIrrelevant. Error Number: 6007. Error Message: Price not close to expiry./
+        /AnchorError thrown in programs\/solana-options\/src\/state.rs:\d+. Error Code: MarkNotFinal. Error Number: 6026. Error Message: Mark is still in its challenge period./
       );
     });
 
-    it("Can set mark price after expiry", async () => {
-      const { seller, program, context, provider, setPrice } =
-        await fixtureDeployed();
-
-      const expiry = new Date();
-      const publishTime = expiry;
-      setPrice(130, publishTime);
-
-      await warpTo(
-        context,
-        new BN(expiry.getTime() / 1000).add(new anchor.BN(10_000))
-      );
+    it("Can reject if option has already been exercised", async () => {
+      const { program, pda, buyer, wsol, context, usdc, expiry } =
+        await fixtureMarked();
 
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
       await program.methods
-        .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
-        .accounts({ priceUpdate })
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
         .rpc();
 
-      expect(
-        await program.account.expiryData.fetch(
-          getExpiryPda({ expiry, programId: program.programId })
-        )
-      ).toStrictEqual({
-        bump: expect.any(Number),
-        conf: expect.toBeBN(new BN(12190053)),
-        payer: seller.publicKey,
-        price: expect.toBeBN(new BN(13000000000)),
-        publishTime: expect.toBeBN(
-          new BN(Math.floor(publishTime.getTime() / 1000))
-        ),
-        exponent: -8,
-      });
-    });
+      // Wait to avoid getting the error "This transaction has already been processed"
+      await new Promise((resolve) => setTimeout(resolve, 1));
 
-    it("Can set mark price", async () => {
-      const { program, setPrice, seller } = await fixtureDeployed();
+      await expect(
+        program.methods
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d+. Error Code: OptionAlreadyExercised. Error Number: 6005. Error Message: Option already exercised./
+      );
+    });
 
-      const publishTime = new Date(Date.now() - 1000);
-      const expiry = new Date();
-      setPrice(130, publishTime);
+    it("Can reject if option hasn't been bought", async () => {
+      const { program, pda, buyer, wsol, context, usdc, setPrice, expiry } =
+        await fixtureInitialized();
 
+      setPrice(4000);
       await program.methods
-        .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .mark(solFeedId, expiry)
         .accounts({ priceUpdate })
         .rpc();
 
-      expect(
-        await program.account.expiryData.fetch(
-          getExpiryPda({ expiry, programId: program.programId })
-        )
-      ).toStrictEqual({
-        bump: expect.any(Number),
-        conf: expect.toBeBN(new BN(12190053)),
-        payer: seller.publicKey,
-        price: expect.toBeBN(new BN(13000000000)),
-        publishTime: expect.toBeBN(
-          new BN(Math.floor(publishTime.getTime() / 1000))
-        ),
-        exponent: -8,
-      });
-    });
-
-    it("Can reject if price is after expiry", async () => {
-      const { program, setPrice } = await fixtureDeployed();
-
-      const expiry = new Date();
-      const publishTime = new Date(expiry.getTime() + 1000);
-      setPrice(130, publishTime);
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
 
       await expect(
         program.methods
-          .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
-          .accounts({ priceUpdate })
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d\d. Error Code: PriceIrrelevant. Error Number: 6007. Error Message: Price not close to expiry./
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d+. Error Code: OptionNotPurchased. Error Number: 6003. Error Message: Option was not purchased./
       );
     });
 
-    it("Can reject update if mark price is further away", async () => {
-      const { program, context, provider, setPrice } = await fixtureDeployed();
-
-      const expiry = new Date();
-
-      setPrice(130, new Date(expiry.getTime() - 1000));
-      await program.methods
-        .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
-        .accounts({ priceUpdate })
-        .rpc();
-
-      const publishTime = new Date(expiry.getTime() - 2000);
-      setPrice(131, publishTime);
-
-      await new Promise((resolve) => setTimeout(resolve, 3));
+    it("Can reject if not buyer", async () => {
+      const { program, pda, seller, wsol, usdc, context } =
+        await fixtureMarked();
 
       await expect(
         program.methods
-          .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
-          .accounts({ priceUpdate })
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: seller.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([seller])
           .rpc()
       ).rejects.toThrowError(
-        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d\d. Error Code: PriceIrrelevant. Error Number: 6007. Error Message: Price not close to expiry./
+        "AnchorError caused by account: buyer. Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated."
       );
     });
 
-    it("Can update if mark price is closer", async () => {
-      const { program, setPrice, seller } = await fixtureDeployed();
+    it("Can reject if option as not expired", async () => {
+      const { program, pda, buyer, wsol, context, usdc, expiry } =
+        await fixtureMarked();
 
-      const expiry = new Date();
+      await expect(
+        program.methods
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d+. Error Code: OptionNotExpired. Error Number: 6006. Error Message: Option has not expired./
+      );
+    });
+  });
 
-      setPrice(130, new Date(expiry.getTime() - 2000));
-      await program.methods
-        .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
-        .accounts({ priceUpdate })
-        .rpc();
+  describe("Close instruction", () => {
+    it("Can successfully close exercised option by seller", async () => {
+      const { program, pda, buyer, wsol, context, usdc, seller } =
+        await fixtureExercised();
 
-      await new Promise((resolve) => setTimeout(resolve, 3));
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(885)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(0));
 
-      const publishTime = new Date(expiry.getTime() - 1000);
-      setPrice(131, publishTime);
       await program.methods
-        .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
-        .accounts({ priceUpdate })
+        .close()
+        .accounts({
+          mintBase: wsol,
+          data: pda,
+          seller: seller.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([seller])
         .rpc();
 
+      // Expect accounts to be close
+      expect(await context.banksClient.getAccount(pda)).to.equal(null);
       expect(
-        await program.account.expiryData.fetch(
-          getExpiryPda({ expiry, programId: program.programId })
+        await context.banksClient.getAccount(
+          token.getAssociatedTokenAddressSync(wsol, pda, true)
         )
-      ).toStrictEqual({
-        bump: expect.any(Number),
-        conf: expect.toBeBN(new BN(12190053)),
-        payer: seller.publicKey,
-        price: expect.toBeBN(new BN(13100000000)),
-        publishTime: expect.toBeBN(
-          new BN(Math.floor(publishTime.getTime() / 1000))
-        ),
-        exponent: -8,
-      });
-    });
-
-    it("Can close mark price", async () => {
-      const { program, setPrice, context } = await fixtureDeployed();
-
-      const expiry = new Date();
-      setPrice(130, expiry);
+      ).to.equal(null);
+      expect(
+        await context.banksClient.getAccount(
+          token.getAssociatedTokenAddressSync(usdc, pda, true)
+        )
+      ).to.equal(null);
+
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(0)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(885));
+    });
+
+    it("Can successfully close exercised option by anyone", async () => {
+      const { program, pda, buyer, wsol, context, usdc, seller, payer } =
+        await fixtureExercised();
+
+      const keeper = Keypair.generate();
+      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
+
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(885)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(0));
+
+      await fundAtaAccount(context.banksClient, usdc, seller, 0);
+
+      await program.methods
+        .close()
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          seller: seller.publicKey,
+          payer: keeper.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc();
+
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(0)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(885));
+    });
+
+    it("Can successfully close unbought option by anyone", async () => {
+      const { program, pda, buyer, wsol, context, usdc, seller } =
+        await fixtureInitialized();
+
+      const keeper = Keypair.generate();
+      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
+
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(1000)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(0));
+
+      await program.methods
+        .close()
+        .accounts({
+          mintBase: wsol,
+          data: pda,
+          seller: seller.publicKey,
+          payer: keeper.publicKey,
+          expiry: null,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc();
+
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(0)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(1000));
+    });
+
+    it("Can successfully close unexercised option after expiry", async () => {
+      const { program, pda, wsol, context, seller, expiry, buyer, setPrice } =
+        await fixtureBought();
+
+      await warpTo(context, expiry.add(new anchor.BN(100)));
+
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(1010)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(0));
 
+      setPrice(3000);
       await program.methods
-        .mark(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .mark(solFeedId, expiry)
         .accounts({ priceUpdate })
         .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
 
       await program.methods
-        .markClose(new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .close()
+        .accounts({
+          mintBase: wsol,
+          data: pda,
+          seller: seller.publicKey,
+          payer: seller.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([seller])
         .rpc();
 
+      // Expect accounts to be close
+      expect(await context.banksClient.getAccount(pda)).to.equal(null);
       expect(
         await context.banksClient.getAccount(
-          getExpiryPda({ expiry, programId: program.programId })
+          token.getAssociatedTokenAddressSync(wsol, pda, true)
         )
       ).to.equal(null);
+
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(0)
+      );
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(1010));
+    });
+
+    it("Can reject closing bought option before expiry", async () => {
+      const {
+        program,
+        pda,
+        wsol,
+        context,
+        usdc,
+        seller,
+        buyer,
+        expiry,
+        setPrice,
+      } = await fixtureBought();
+
+      await expect(
+        program.methods
+          .close()
+          .accounts({
+            data: pda,
+            mintQuote: usdc,
+            mintBase: wsol,
+            payer: seller.publicKey,
+            seller: seller.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([seller])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/close.rs:\d+. Error Code: OptionCannotBeClosedYet. Error Number: 6004. Error Message: Option cannot be closed Yet./
+      );
+    });
+
+    it("Can reject closing unmarked option within the grace period", async () => {
+      const { program, pda, wsol, context, seller, expiry } =
+        await fixtureBought();
+
+      await warpTo(context, expiry.add(new anchor.BN(100)));
+
+      await expect(
+        program.methods
+          .close()
+          .accounts({
+            mintBase: wsol,
+            data: pda,
+            payer: seller.publicKey,
+            seller: seller.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([seller])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/close.rs:\d+. Error Code: OptionCannotBeClosedYet. Error Number: 6004. Error Message: Option cannot be closed Yet./
+      );
+    });
+
+    it("Can reject closing bought option without its expiry", async () => {
+      const { program, pda, wsol, context, seller, expiry, setPrice } =
+        await fixtureBought();
+
+      // In the money, so leaving out the mark must not make the option lapse
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(3 * 24 * 60 * 60)));
+
+      await expect(
+        program.methods
+          .close()
+          .accounts({
+            mintBase: wsol,
+            data: pda,
+            payer: seller.publicKey,
+            seller: seller.publicKey,
+            expiry: null,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([seller])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/close.rs:\d+. Error Code: OptionNotMarked. Error Number: 6008. Error Message: Option not marked./
+      );
+    });
+
+    it("Can close unmarked option after the grace period", async () => {
+      const { program, pda, wsol, context, seller, expiry } =
+        await fixtureBought();
+
+      // Nobody marked the expiry within 3 days
+      await warpTo(context, expiry.add(new anchor.BN(3 * 24 * 60 * 60)));
+
+      await program.methods
+        .close()
+        .accounts({
+          mintBase: wsol,
+          data: pda,
+          payer: seller.publicKey,
+          seller: seller.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([seller])
+        .rpc();
+
+      expect(await context.banksClient.getAccount(pda)).to.equal(null);
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(1010));
+    });
+  });
+
+  describe("Cancel instruction", () => {
+    it("Can cancel unbought option by seller", async () => {
+      const { program, pda, wsol, context, seller } = await fixtureInitialized();
+
+      await program.methods
+        .cancel("Changed my mind")
+        .accounts({
+          mintBase: wsol,
+          data: pda,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(1000));
+      expect(await context.banksClient.getAccount(pda)).to.equal(null);
+    });
+
+    it("Can reject cancel by anyone but the seller", async () => {
+      const { program, pda, wsol, context, buyer } = await fixtureInitialized();
+
+      await fundAtaAccount(context.banksClient, wsol, buyer, BigInt(0));
+      await expect(
+        program.methods
+          .cancel("")
+          .accounts({
+            seller: buyer.publicKey,
+            mintBase: wsol,
+            data: pda,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: data. Error Code: ConstraintSeeds. Error Number: 2006. Error Message: A seeds constraint was violated."
+      );
+    });
+
+    it("Can reject cancel once bought", async () => {
+      const { program, pda, wsol } = await fixtureBought();
+
+      await expect(
+        program.methods
+          .cancel("")
+          .accounts({
+            mintBase: wsol,
+            data: pda,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/cancel.rs:\d+. Error Code: OptionAlreadyBought. Error Number: 6002. Error Message: Option is already bought./
+      );
+    });
+  });
+
+  describe("Transfer position instruction", () => {
+    it("Can transfer position to a new buyer", async () => {
+      const { program, pda, buyer, wsol, usdc, context, setPrice, expiry } =
+        await fixtureBought();
+
+      const keeper = Keypair.generate();
+      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
+      await fundAtaAccount(context.banksClient, wsol, keeper, 0);
+
+      await program.methods
+        .transferPosition()
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          newBuyer: keeper.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect((await program.account.coveredCall.fetch(pda)).buyer).toEqual(
+        keeper.publicKey
+      );
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await expect(
+        program.methods
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: buyer. Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated."
+      );
+
+      await program.methods
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: keeper.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, keeper.publicKey)
+      ).to.equal(BigInt(125));
+    });
+
+    it("Can reject if not buyer", async () => {
+      const { program, pda, seller } = await fixtureBought();
+
+      await expect(
+        program.methods
+          .transferPosition()
+          .accounts({
+            data: pda,
+            buyer: seller.publicKey,
+            newBuyer: seller.publicKey,
+          })
+          .signers([seller])
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: buyer. Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated."
+      );
+    });
+
+    it("Can reject if option has expired", async () => {
+      const { program, pda, buyer, seller, context, expiry } =
+        await fixtureBought();
+
+      await warpTo(context, expiry.add(new anchor.BN(100)));
+
+      await expect(
+        program.methods
+          .transferPosition()
+          .accounts({
+            data: pda,
+            buyer: buyer.publicKey,
+            newBuyer: seller.publicKey,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/transfer_position.rs:\d+. Error Code: OptionExpired. Error Number: 6001. Error Message: Option has expired./
+      );
+    });
+
+    it("Can transfer every contract of a position", async () => {
+      const { program, wsol, usdc, buyer, seller } = await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(10),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      await program.methods
+        .transferPosition()
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          newBuyer: seller.publicKey,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect((await program.account.coveredCall.fetch(pda)).buyer).toEqual(
+        seller.publicKey
+      );
+    });
+  });
+
+  describe("Tokenized options", () => {
+    it("Can exercise by whoever holds the option token", async () => {
+      const { context, program, wsol, usdc, seller, buyer, payer, setPrice } =
+        await fixtureDeployed();
+      const mintOption = Keypair.generate();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: null,
+          mintOption: mintOption.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([mintOption])
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          mintOption: mintOption.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          mintOption.publicKey,
+          buyer.publicKey
+        )
+      ).to.equal(BigInt(1));
+
+      // Sell the position to a keeper
+      const keeper = Keypair.generate();
+      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
+      await fundAtaAccount(context.banksClient, wsol, keeper, 0);
+      const ataKeeperOption = await createAssociatedTokenAccount(
+        context.banksClient,
+        payer,
+        mintOption.publicKey,
+        keeper.publicKey
+      );
+      await transfer(
+        context.banksClient,
+        payer,
+        token.getAssociatedTokenAddressSync(
+          mintOption.publicKey,
+          buyer.publicKey
+        ),
+        ataKeeperOption,
+        buyer,
+        1
+      );
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await program.methods
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: keeper.publicKey,
+          mintOption: mintOption.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, keeper.publicKey)
+      ).to.equal(BigInt(125));
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          mintOption.publicKey,
+          keeper.publicKey
+        )
+      ).to.equal(BigInt(0));
+    });
+  });
+
+  describe("Token-2022 mints", () => {
+    const fixtureFeeMint = async () => {
+      const fixture = await fixtureDeployed();
+      const { context, seller, buyer } = fixture;
+
+      // 1% transfer fee on the base
+      const feeMint = await createMint2022(
+        context,
+        9,
+        [token.ExtensionType.TransferFeeConfig],
+        (mint) => [
+          token.createInitializeTransferFeeConfigInstruction(
+            mint,
+            authority.publicKey,
+            authority.publicKey,
+            100,
+            BigInt(1_000_000),
+            token.TOKEN_2022_PROGRAM_ID
+          ),
+        ]
+      );
+      await Promise.all([
+        fundAtaAccount(
+          context.banksClient,
+          feeMint,
+          seller,
+          BigInt(1100),
+          token.TOKEN_2022_PROGRAM_ID
+        ),
+        fundAtaAccount(
+          context.banksClient,
+          feeMint,
+          buyer,
+          BigInt(0),
+          token.TOKEN_2022_PROGRAM_ID
+        ),
+      ]);
+      return { ...fixture, feeMint };
+    };
+
+    const initializeFeeMint = async (
+      fixture: Awaited<ReturnType<typeof fixtureFeeMint>>
+    ) => {
+      const { program, feeMint, wsol, usdc, buyer, seller } = fixture;
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          true,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: feeMint,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_2022_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: feeMint,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+      return { expiry, pda };
+    };
+
+    it("Can hold the full collateral and pay the buyer net of fees", async () => {
+      const fixture = await fixtureFeeMint();
+      const { program, context, feeMint, wsol, usdc, buyer, seller, setPrice } =
+        fixture;
+      const { expiry, pda } = await initializeFeeMint(fixture);
+
+      // Seller pays 1011 so the vault holds 1000 after the 11 fee
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          feeMint,
+          pda,
+          token.TOKEN_2022_PROGRAM_ID
+        )
+      ).to.equal(BigInt(1000));
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          feeMint,
+          seller.publicKey,
+          token.TOKEN_2022_PROGRAM_ID
+        )
+      ).to.equal(BigInt(1100 - 1011));
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_2022_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+      await program.methods
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: feeMint,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_2022_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // Vault pays out 125, of which 2 is withheld as the transfer fee
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          feeMint,
+          buyer.publicKey,
+          token.TOKEN_2022_PROGRAM_ID
+        )
+      ).to.equal(BigInt(123));
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          feeMint,
+          pda,
+          token.TOKEN_2022_PROGRAM_ID
+        )
+      ).to.equal(BigInt(1000 - 125));
+    });
+
+    it("Can close a vault holding withheld fees", async () => {
+      const fixture = await fixtureFeeMint();
+      const { program, context, feeMint, seller } = fixture;
+      const { pda } = await initializeFeeMint(fixture);
+
+      await program.methods
+        .close()
+        .accounts({
+          mintBase: feeMint,
+          data: pda,
+          seller: seller.publicKey,
+          expiry: null,
+          tokenProgram: token.TOKEN_2022_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      // Withheld fees were harvested to the mint so the vault could be closed
+      expect(
+        await context.banksClient.getAccount(
+          token.getAssociatedTokenAddressSync(
+            feeMint,
+            pda,
+            true,
+            token.TOKEN_2022_PROGRAM_ID
+          )
+        )
+      ).to.equal(null);
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          feeMint,
+          seller.publicKey,
+          token.TOKEN_2022_PROGRAM_ID
+        )
+      ).to.equal(BigInt(1100 - 1011 + 990));
+    });
+
+    const unsupportedExtensions = [
+      {
+        extension: token.ExtensionType.PermanentDelegate,
+        getInstruction: (mint: PublicKey) =>
+          token.createInitializePermanentDelegateInstruction(
+            mint,
+            authority.publicKey,
+            token.TOKEN_2022_PROGRAM_ID
+          ),
+      },
+      {
+        extension: token.ExtensionType.NonTransferable,
+        getInstruction: (mint: PublicKey) =>
+          token.createInitializeNonTransferableMintInstruction(
+            mint,
+            token.TOKEN_2022_PROGRAM_ID
+          ),
+      },
+      {
+        extension: token.ExtensionType.TransferHook,
+        getInstruction: (mint: PublicKey) =>
+          token.createInitializeTransferHookInstruction(
+            mint,
+            authority.publicKey,
+            Keypair.generate().publicKey,
+            token.TOKEN_2022_PROGRAM_ID
+          ),
+      },
+    ];
+
+    for (const { extension, getInstruction } of unsupportedExtensions) {
+      it(`Can reject a ${token.ExtensionType[extension]} base mint`, async () => {
+        const { context, program, usdc, wsol, seller } =
+          await fixtureDeployed();
+        const mintBase = await createMint2022(
+          context,
+          9,
+          [extension],
+          (mint) => [getInstruction(mint)]
+        );
+        await fundAtaAccount(
+          context.banksClient,
+          mintBase,
+          seller,
+          BigInt(1000),
+          token.TOKEN_2022_PROGRAM_ID
+        );
+
+        const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+        await expect(
+          program.methods
+            .initialize(
+              new anchor.BN("1000"),
+              new anchor.BN("3500"),
+              new anchor.BN(expiry),
+              solFeedId,
+              new anchor.BN(10),
+              false,
+              { cash: {} },
+              { european: {} },
+              new anchor.BN(1),
+              200,
+              new anchor.BN(0),
+              { mark: {} }
+            )
+            .accounts({
+              mintBase,
+              mintPremium: wsol,
+              mintQuote: usdc,
+              buyer: null,
+              tokenProgram: token.TOKEN_2022_PROGRAM_ID,
+            })
+            .rpc()
+        ).rejects.toThrowError(
+          /AnchorError thrown in programs\/solana-options\/src\/mint.rs:\d+. Error Code: UnsupportedMintExtension. Error Number: 6013. Error Message: Mint extension is not supported./
+        );
+      });
+    }
+  });
+
+  describe("Physical settlement", () => {
+    it("Can deliver base for quote and let the seller collect it", async () => {
+      const { context, program, wsol, usdc, seller, buyer, setPrice } =
+        await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { physical: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      // Buyer pays the full quote amount for the full base amount
+      await fundAtaAccount(context.banksClient, usdc, buyer, BigInt(3500));
+      await program.methods
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramQuote: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(1990));
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, buyer.publicKey)
+      ).to.equal(BigInt(0));
+
+      // Seller collects the quote and the premium
+      await fundAtaAccount(context.banksClient, usdc, seller, BigInt(0));
+      await program.methods
+        .close()
+        .accounts({
+          data: pda,
+          seller: seller.publicKey,
+          payer: seller.publicKey,
+          mintQuote: usdc,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          tokenProgramQuote: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, seller.publicKey)
+      ).to.equal(BigInt(3500));
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(10));
+    });
+
+    it("Can exercise a physical option without a mark", async () => {
+      const { context, program, wsol, usdc, seller, buyer } =
+        await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { physical: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // No mark is ever set, the swap happens at the strike
+      await warpTo(context, expiry);
+
+      await fundAtaAccount(context.banksClient, usdc, buyer, BigInt(3500));
+      await program.methods
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramQuote: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(1990));
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, buyer.publicKey)
+      ).to.equal(BigInt(0));
+
+      // Seller collects the quote and the premium
+      await fundAtaAccount(context.banksClient, usdc, seller, BigInt(0));
+      await program.methods
+        .close()
+        .accounts({
+          data: pda,
+          seller: seller.publicKey,
+          payer: seller.publicKey,
+          mintQuote: usdc,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          tokenProgramQuote: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, seller.publicKey)
+      ).to.equal(BigInt(3500));
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(10));
+    });
+
+    it("Can reject a physical exercise without the quote accounts", async () => {
+      const { context, program, wsol, usdc, seller, buyer, setPrice } =
+        await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { physical: {} },
+          { european: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await expect(
+        program.methods
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d+. Error Code: QuoteAccountMissing. Error Number: 6015. Error Message: Quote token account was not provided./
+      );
+    });
+  });
+
+  describe("American exercise", () => {
+    const fixtureAmerican = async () => {
+      const fixture = await fixtureDeployed();
+      const { program, wsol, usdc, seller, buyer } = fixture;
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { american: {} },
+          new anchor.BN(1),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      return { ...fixture, expiry, pda };
+    };
+
+    it("Can exercise before expiry with a fresh price", async () => {
+      const { context, program, pda, wsol, usdc, seller, buyer, setPrice } =
+        await fixtureAmerican();
+
+      setPrice(4000);
+      await program.methods
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          priceUpdate,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(1115));
+
+      // Seller can close once exercised, even before expiry
+      await program.methods
+        .close()
+        .accounts({
+          data: pda,
+          seller: seller.publicKey,
+          payer: seller.publicKey,
+          expiry: null,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(885));
+    });
+
+    it("Can reject an early exercise with a stale price", async () => {
+      const { program, pda, wsol, usdc, buyer, setPrice } =
+        await fixtureAmerican();
+
+      setPrice(4000, new Date(Date.now() - 5 * 60 * 1000));
+      await expect(
+        program.methods
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            expiry: null,
+            priceUpdate,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(/Error Code: PriceTooOld/);
+    });
+
+    it("Can reject an early exercise without a price", async () => {
+      const { program, pda, wsol, usdc, buyer } = await fixtureAmerican();
+
+      await expect(
+        program.methods
+          .exercise(new anchor.BN(1))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            expiry: null,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d+. Error Code: PriceUpdateMissing. Error Number: 6016. Error Message: Price update account was not provided./
+      );
+    });
+  });
+
+  describe("Partial exercise", () => {
+    it("Can exercise part of a tokenized position and keep the rest live", async () => {
+      const { context, program, wsol, usdc, seller, buyer, payer, setPrice } =
+        await fixtureDeployed();
+      const mintOption = Keypair.generate();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initialize(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          { cash: {} },
+          { european: {} },
+          new anchor.BN(10),
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: wsol,
+          mintQuote: usdc,
+          buyer: null,
+          mintOption: mintOption.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([mintOption])
+        .rpc();
+
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          mintOption: mintOption.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          mintOption.publicKey,
+          buyer.publicKey
+        )
+      ).to.equal(BigInt(10));
+
+      // Sell 4 of the 10 contracts to a keeper
+      const keeper = Keypair.generate();
+      await airdrop(context, keeper.publicKey, 1 * LAMPORTS_PER_SOL);
+      await fundAtaAccount(context.banksClient, wsol, keeper, 0);
+      const ataKeeperOption = await createAssociatedTokenAccount(
+        context.banksClient,
+        payer,
+        mintOption.publicKey,
+        keeper.publicKey
+      );
+      await transfer(
+        context.banksClient,
+        payer,
+        token.getAssociatedTokenAddressSync(
+          mintOption.publicKey,
+          buyer.publicKey
+        ),
+        ataKeeperOption,
+        buyer,
+        4
+      );
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await program.methods
+        .exercise(new anchor.BN(4))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: keeper.publicKey,
+          mintOption: mintOption.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([keeper])
+        .rpc();
+
+      // 4 contracts of 100 base each, 12.5% in the money
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, keeper.publicKey)
+      ).to.equal(BigInt(50));
+
+      const data = await program.account.coveredCall.fetch(pda);
+      expect(data.contractsExercised).toBeBN(new anchor.BN(4));
+      expect(data.isExercised).to.equal(false);
+
+      // Cannot exercise more than is open
+      await expect(
+        program.methods
+          .exercise(new anchor.BN(7))
+          .accounts({
+            mintBase: wsol,
+            mintQuote: usdc,
+            data: pda,
+            buyer: buyer.publicKey,
+            mintOption: mintOption.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise.rs:\d+. Error Code: InvalidQuantity. Error Number: 6018. Error Message: Quantity exceeds the open contracts./
+      );
+
+      await program.methods
+        .exercise(new anchor.BN(6))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          mintOption: mintOption.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(1065));
+      expect(
+        (await program.account.coveredCall.fetch(pda)).isExercised
+      ).to.equal(true);
+    });
+
+    it("Can reject contracts that do not divide the amounts", async () => {
+      const { program, wsol, usdc, buyer } = await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await expect(
+        program.methods
+          .initialize(
+            new anchor.BN("1000"),
+            new anchor.BN("3500"),
+            new anchor.BN(expiry),
+            solFeedId,
+            new anchor.BN(10),
+            false,
+            { cash: {} },
+            { european: {} },
+            new anchor.BN(3),
+            200,
+            new anchor.BN(0),
+            { mark: {} }
+          )
+          .accounts({
+            mintBase: wsol,
+            mintPremium: wsol,
+            mintQuote: usdc,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/initialize.rs:\d+. Error Code: InvalidContracts. Error Number: 6017. Error Message: Amounts must divide evenly into contracts./
+      );
+    });
+  });
+
+  describe("Option series", () => {
+    // Series where the seller writes 4 contracts and a second writer 6
+    const fixtureSeriesWritten = async (isAmerican = false) => {
+      const fixture = await fixtureDeployed();
+      const { context, program, wsol, usdc, seller } = fixture;
+      const writer = Keypair.generate();
+      await airdrop(context, writer.publicKey, 1 * LAMPORTS_PER_SOL);
+      await fundAtaAccount(context.banksClient, wsol, writer, BigInt(1000));
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      const series = getSeriesPda({
+        amountBase: 100n,
+        amountQuote: 350n,
+        expiry: BigInt(expiry.toString()),
+        feedId: solFeedId,
+        isAmerican,
+        mintBase: wsol,
+        mintQuote: usdc,
+        programId: program.programId,
+      });
+
+      await program.methods
+        .initializeSeries(
+          new anchor.BN(100),
+          new anchor.BN(350),
+          expiry,
+          solFeedId,
+          isAmerican ? { american: {} } : { european: {} }
+        )
+        .accounts({
+          series,
+          mintBase: wsol,
+          mintQuote: usdc,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      for (const [from, contracts] of [
+        [seller, 4],
+        [writer, 6],
+      ] as const) {
+        await program.methods
+          .writeSeries(new anchor.BN(contracts), new anchor.BN(1))
+          .accounts({
+            seller: from.publicKey,
+            series,
+            mintBase: wsol,
+            mintPremium: wsol,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([from])
+          .rpc();
+      }
+
+      const { mintOption } = await program.account.optionSeries.fetch(series);
+      const getWriterPda = (from: Keypair) =>
+        PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("series-writer"),
+            series.toBuffer(),
+            from.publicKey.toBuffer(),
+          ],
+          program.programId
+        )[0];
+
+      const buyFrom = (from: Keypair, contracts: number) =>
+        program.methods
+          .buySeries(new anchor.BN(contracts), new anchor.BN(contracts))
+          .accounts({
+            payer: fixture.buyer.publicKey,
+            buyer: fixture.buyer.publicKey,
+            series,
+            writer: getWriterPda(from),
+            mintPremium: wsol,
+            mintOption,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([fixture.buyer])
+          .rpc();
+
+      const withdraw = (from: Keypair) =>
+        program.methods
+          .withdrawSeries()
+          .accounts({
+            seller: from.publicKey,
+            series,
+            mintBase: wsol,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([from])
+          .rpc();
+
+      return {
+        expiry,
+        series,
+        mintOption,
+        writer,
+        getWriterPda,
+        buyFrom,
+        withdraw,
+        ...fixture,
+      };
+    };
+
+    it("Can take the mark window and tolerance from the config", async () => {
+      const { program, payer, wsol, usdc } = await fixtureDeployed();
+
+      await program.methods
+        .updateConfig({
+          feeBpsPremium: 0,
+          feeBpsSettlement: 0,
+          markWindow: new anchor.BN(600),
+          maxConfBps: 100,
+          allowedFeeds: [],
+          isWritePaused: false,
+          isBuyPaused: false,
+          isMarkPaused: false,
+        })
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      const series = getSeriesPda({
+        amountBase: 100n,
+        amountQuote: 350n,
+        expiry: BigInt(expiry.toString()),
+        feedId: solFeedId,
+        isAmerican: false,
+        mintBase: wsol,
+        mintQuote: usdc,
+        programId: program.programId,
+      });
+
+      await program.methods
+        .initializeSeries(
+          new anchor.BN(100),
+          new anchor.BN(350),
+          expiry,
+          solFeedId,
+          { european: {} }
+        )
+        .accounts({
+          series,
+          mintBase: wsol,
+          mintQuote: usdc,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const { markWindow, maxConfBps } =
+        await program.account.optionSeries.fetch(series);
+      expect(markWindow).toBeBN(new anchor.BN(600));
+      expect(maxConfBps).to.equal(100);
+    });
+
+    it("Can pool writers and settle them pro-rata", async () => {
+      const { context, program, wsol, usdc, seller, buyer, setPrice } =
+        await fixtureDeployed();
+      const writer = Keypair.generate();
+      await airdrop(context, writer.publicKey, 1 * LAMPORTS_PER_SOL);
+      await fundAtaAccount(context.banksClient, wsol, writer, BigInt(1000));
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      const series = getSeriesPda({
+        amountBase: 100n,
+        amountQuote: 350n,
+        expiry: BigInt(expiry.toString()),
+        feedId: solFeedId,
+        isAmerican: false,
+        mintBase: wsol,
+        mintQuote: usdc,
+        programId: program.programId,
+      });
+
+      await program.methods
+        .initializeSeries(
+          new anchor.BN(100),
+          new anchor.BN(350),
+          expiry,
+          solFeedId,
+          { european: {} }
+        )
+        .accounts({
+          series,
+          mintBase: wsol,
+          mintQuote: usdc,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      // Two writers deposit 4 and 6 contracts
+      await program.methods
+        .writeSeries(new anchor.BN(4), new anchor.BN(1))
+        .accounts({
+          series,
+          mintBase: wsol,
+          mintPremium: wsol,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      await program.methods
+        .writeSeries(new anchor.BN(6), new anchor.BN(1))
+        .accounts({
+          seller: writer.publicKey,
+          series,
+          mintBase: wsol,
+          mintPremium: wsol,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([writer])
+        .rpc();
+
+      // Buyer takes 2 contracts from the first and 3 from the second
+      const { mintOption } = await program.account.optionSeries.fetch(series);
+      for (const [from, contracts] of [
+        [seller, 2],
+        [writer, 3],
+      ] as const) {
+        await program.methods
+          .buySeries(new anchor.BN(contracts), new anchor.BN(contracts))
+          .accounts({
+            payer: buyer.publicKey,
+            buyer: buyer.publicKey,
+            series,
+            writer: PublicKey.findProgramAddressSync(
+              [
+                Buffer.from("series-writer"),
+                series.toBuffer(),
+                from.publicKey.toBuffer(),
+              ],
+              program.programId
+            )[0],
+            mintPremium: wsol,
+            mintOption,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc();
+      }
+
+      expect(
+        await getAtaTokenBalance(
+          context.banksClient,
+          mintOption,
+          buyer.publicKey
+        )
+      ).to.equal(BigInt(5));
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await program.methods
+        .exerciseSeries(new anchor.BN(5))
+        .accounts({
+          buyer: buyer.publicKey,
+          series,
+          mintBase: wsol,
+          mintOption,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // 500 base in the money by 12.5%
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(1058));
+
+      // Writers split the remaining 437 of sold collateral 2:3
+      await program.methods
+        .withdrawSeries()
+        .accounts({
+          series,
+          mintBase: wsol,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      await program.methods
+        .withdrawSeries()
+        .accounts({
+          seller: writer.publicKey,
+          series,
+          mintBase: wsol,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([writer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(976));
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, writer.publicKey)
+      ).to.equal(BigInt(965));
+    });
+
+    it("Can reject buying more contracts than a writer has open", async () => {
+      const { program, wsol, usdc, seller, buyer } = await fixtureDeployed();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      const series = getSeriesPda({
+        amountBase: 100n,
+        amountQuote: 350n,
+        expiry: BigInt(expiry.toString()),
+        feedId: solFeedId,
+        isAmerican: false,
+        mintBase: wsol,
+        mintQuote: usdc,
+        programId: program.programId,
+      });
+
+      await program.methods
+        .initializeSeries(
+          new anchor.BN(100),
+          new anchor.BN(350),
+          expiry,
+          solFeedId,
+          { european: {} }
+        )
+        .accounts({
+          series,
+          mintBase: wsol,
+          mintQuote: usdc,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+      await program.methods
+        .writeSeries(new anchor.BN(2), new anchor.BN(1))
+        .accounts({
+          series,
+          mintBase: wsol,
+          mintPremium: wsol,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const { mintOption } = await program.account.optionSeries.fetch(series);
+      await expect(
+        program.methods
+          .buySeries(new anchor.BN(3), new anchor.BN(3))
+          .accounts({
+            payer: buyer.publicKey,
+            buyer: buyer.publicKey,
+            series,
+            writer: PublicKey.findProgramAddressSync(
+              [
+                Buffer.from("series-writer"),
+                series.toBuffer(),
+                seller.publicKey.toBuffer(),
+              ],
+              program.programId
+            )[0],
+            mintPremium: wsol,
+            mintOption,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/buy_series.rs:\d+. Error Code: ContractsUnavailable. Error Number: 6019. Error Message: Not enough contracts are available from this writer./
+      );
+    });
+
+    it("Can withdraw unsold collateral before expiry", async () => {
+      const {
+        context,
+        program,
+        wsol,
+        seller,
+        getWriterPda,
+        buyFrom,
+        withdraw,
+      } = await fixtureSeriesWritten();
+
+      await buyFrom(seller, 2);
+      await withdraw(seller);
+
+      // 400 written, 2 premium and the 2 unsold contracts back
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(802));
+
+      // Sold contracts stay with the writer until expiry
+      const { contractsWritten, contractsSold } =
+        await program.account.seriesWriter.fetch(getWriterPda(seller));
+      expect(contractsWritten).toBeBN(new anchor.BN(2));
+      expect(contractsSold).toBeBN(new anchor.BN(2));
+    });
+
+    it("Can settle writers net of an early american exercise", async () => {
+      const {
+        context,
+        program,
+        wsol,
+        seller,
+        buyer,
+        writer,
+        series,
+        mintOption,
+        expiry,
+        setPrice,
+        buyFrom,
+        withdraw,
+      } = await fixtureSeriesWritten(true);
+
+      await buyFrom(seller, 2);
+      await buyFrom(writer, 3);
+
+      setPrice(4000);
+      await program.methods
+        .exerciseSeries(new anchor.BN(2))
+        .accounts({
+          buyer: buyer.publicKey,
+          series,
+          mintBase: wsol,
+          mintOption,
+          priceUpdate,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // 200 base in the money by 12.5%
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(1020));
+      const { amountBasePaid } =
+        await program.account.optionSeries.fetch(series);
+      expect(amountBasePaid).toBeBN(new anchor.BN(25));
+
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      // The pool of 500 has paid 25 and owes 13 on each of the 3 open
+      // contracts, the seller takes 2/5 of the remaining 436
+      await withdraw(seller);
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(976));
+    });
+
+    it("Can lapse a series without a mark", async () => {
+      const {
+        context,
+        program,
+        wsol,
+        seller,
+        buyer,
+        series,
+        mintOption,
+        expiry,
+        buyFrom,
+        withdraw,
+      } = await fixtureSeriesWritten();
+
+      await buyFrom(seller, 2);
+      await warpTo(context, expiry.add(new anchor.BN(3 * 24 * 60 * 60)));
+
+      // The seller gets all 400 written back, sold collateral included
+      await withdraw(seller);
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(1002));
+      const { isLapsed } = await program.account.optionSeries.fetch(series);
+      expect(isLapsed).to.equal(true);
+
+      await expect(
+        program.methods
+          .exerciseSeries(new anchor.BN(2))
+          .accounts({
+            buyer: buyer.publicKey,
+            series,
+            mintBase: wsol,
+            mintOption,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/exercise_series.rs:\d+. Error Code: OptionLapsed. Error Number: 6024. Error Message: Option lapsed without a mark./
+      );
+    });
+
+    it("Can settle a second writer after the first and after holders exercised", async () => {
+      const {
+        context,
+        program,
+        wsol,
+        seller,
+        buyer,
+        writer,
+        series,
+        mintOption,
+        expiry,
+        setPrice,
+        buyFrom,
+        withdraw,
+      } = await fixtureSeriesWritten();
+
+      await buyFrom(seller, 2);
+      await buyFrom(writer, 3);
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      // First writer leaves while all 5 contracts are open, each owing 13
+      await withdraw(seller);
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(976));
+
+      await program.methods
+        .exerciseSeries(new anchor.BN(5))
+        .accounts({
+          buyer: buyer.publicKey,
+          series,
+          mintBase: wsol,
+          mintOption,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(1058));
+
+      // Second writer takes 3/5 of the 437 left after the 63 paid out
+      await withdraw(writer);
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, writer.publicKey)
+      ).to.equal(BigInt(965));
+    });
+  });
+
+  describe("Put instructions", () => {
+    const fixturePutInitialized = async () => {
+      const fixture = await fixtureDeployed();
+      const { context, program, wsol, usdc, buyer, seller } = fixture;
+      await Promise.all([
+        fundAtaAccount(context.banksClient, usdc, seller, BigInt(3500)),
+        fundAtaAccount(context.banksClient, usdc, buyer, BigInt(1000)),
+      ]);
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await program.methods
+        .initializePut(
+          new anchor.BN("1000"),
+          new anchor.BN("3500"),
+          new anchor.BN(expiry),
+          solFeedId,
+          new anchor.BN(10),
+          false,
+          200,
+          new anchor.BN(0),
+          { mark: {} }
+        )
+        .accounts({
+          mintBase: wsol,
+          mintPremium: usdc,
+          mintQuote: usdc,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      const pda = getPutPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+
+      return {
+        expiry,
+        pda,
+        ...fixture,
+      };
+    };
+
+    const fixturePutBought = async () => {
+      const fixture = await fixturePutInitialized();
+      const { program, pda, buyer, usdc } = fixture;
+
+      await program.methods
+        .buyPut(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: usdc,
+          payer: buyer.publicKey,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      return fixture;
+    };
+
+    it("Can initialize put", async () => {
+      const { program, pda, context, usdc, wsol, seller, buyer, expiry } =
+        await fixturePutInitialized();
+
+      expect(await program.account.cashSecuredPut.fetch(pda)).toStrictEqual({
+        amountBase: expect.toBeBN(new anchor.BN(1000)),
+        amountPremium: null,
+        amountPremiumAsk: expect.toBeBN(new anchor.BN(10)),
+        amountQuote: expect.toBeBN(new anchor.BN(3500)),
+        bump: expect.any(Number),
+        buyer: buyer.publicKey,
+        decimalsBase: 9,
+        decimalsQuote: 6,
+        feedId: solFeedId,
+        isExercised: false,
+        isPremiumToSeller: false,
+        markWindow: expect.toBeBN(new anchor.BN(1800)),
+        maxConfBps: 200,
+        mintBase: wsol,
+        mintPremium: usdc,
+        mintQuote: usdc,
+        priceSource: { mark: {} },
+        seller: seller.publicKey,
+        timestampExpiry: expect.toBeBN(expiry),
+        timestampCreated: expect.any(BN),
+      });
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, seller.publicKey)
+      ).to.equal(BigInt(0));
+      expect(await getAtaTokenBalance(context.banksClient, usdc, pda)).to.equal(
+        BigInt(3500)
+      );
+    });
+
+    it("Can reject buy put if premium is not in the premium mint", async () => {
+      const { program, pda, buyer, wsol } = await fixturePutInitialized();
+
+      await expect(
+        program.methods
+          .buyPut(new anchor.BN(10))
+          .accounts({
+            payer: buyer.publicKey,
+            data: pda,
+            buyer: buyer.publicKey,
+            mintPremium: wsol,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: mint_premium. Error Code: ConstraintRaw. Error Number: 2003. Error Message: A raw constraint was violated."
+      );
+    });
+
+    it("Can exercise put in the money", async () => {
+      const { program, pda, buyer, wsol, usdc, context, setPrice, expiry } =
+        await fixturePutBought();
+
+      expect(await getAtaTokenBalance(context.banksClient, usdc, pda)).to.equal(
+        BigInt(3510)
+      );
+
+      setPrice(3000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await program.methods
+        .exercisePut()
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // Strike 3500, mark 3000 pays 3500 * 500 / 3500
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, buyer.publicKey)
+      ).to.equal(BigInt(990 + 500));
+      expect(await getAtaTokenBalance(context.banksClient, usdc, pda)).to.equal(
+        BigInt(3510 - 500)
+      );
+    });
+
+    it("Can close put out of the money after expiry", async () => {
+      const { program, pda, usdc, context, seller, buyer, setPrice, expiry } =
+        await fixturePutBought();
+
+      await warpTo(context, expiry.add(new anchor.BN(100)));
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+
+      await program.methods
+        .closePut()
+        .accounts({
+          mintQuote: usdc,
+          data: pda,
+          seller: seller.publicKey,
+          payer: seller.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([seller])
+        .rpc();
+
+      expect(await context.banksClient.getAccount(pda)).to.equal(null);
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, seller.publicKey)
+      ).to.equal(BigInt(3510));
+    });
+
+    it("Can reject closing bought put before expiry", async () => {
+      const { program, pda, usdc, seller, buyer } = await fixturePutBought();
+
+      await expect(
+        program.methods
+          .closePut()
+          .accounts({
+            mintQuote: usdc,
+            data: pda,
+            seller: seller.publicKey,
+            payer: seller.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([seller])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/close_put.rs:\d+. Error Code: OptionCannotBeClosedYet. Error Number: 6004. Error Message: Option cannot be closed Yet./
+      );
+    });
+
+    it("Can cancel put before it is bought", async () => {
+      const { program, pda, usdc, context, seller } =
+        await fixturePutInitialized();
+
+      await program.methods
+        .cancelPut("Changed my mind")
+        .accounts({
+          mintQuote: usdc,
+          data: pda,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, usdc, seller.publicKey)
+      ).to.equal(BigInt(3500));
+      expect(await context.banksClient.getAccount(pda)).to.equal(null);
+    });
+
+    it("Can reject cancel put once bought", async () => {
+      const { program, pda, usdc } = await fixturePutBought();
+
+      await expect(
+        program.methods
+          .cancelPut("")
+          .accounts({
+            mintQuote: usdc,
+            data: pda,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/cancel_put.rs:\d+. Error Code: OptionAlreadyBought. Error Number: 6002. Error Message: Option is already bought./
+      );
+    });
+  });
+
+  it("has correct price update account", async () => {
+    const { provider } = await fixtureDeployed();
+
+    const SOL_PRICE_FEED_ID =
+      "0xef0d8b6fda2ceba41da15d4095d1da392a0d2f8ed0c6c7bc0f4cfac8c280b56d";
+
+    const pyth = new PythSolanaReceiver(provider);
+    expect(pyth.getPriceFeedAccountAddress(0, SOL_PRICE_FEED_ID)).to.deep.equal(
+      priceUpdate
+    );
+  });
+
+  describe("Can set mark price", () => {
+    it("Can reject if mark price is no close enough to expiry", async () => {
+      const { program, context, provider, setPrice } = await fixtureDeployed();
+
+      const expiry = new Date();
+      // Just outside the widest window an option may choose, tighter windows are
+      // checked at exercise
+      const publishTime = new Date(expiry.getTime() - 24 * 60 * 60 * 1000 - 1);
+      setPrice(130, publishTime);
+
+      await expect(
+        program.methods
+          .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+          .accounts({ priceUpdate })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d+. Error Code: PriceIrrelevant. Error Number: 6007. Error Message: Price not close to expiry./
+      );
+    });
+
+    it("Can reject if price update is for another feed", async () => {
+      const { program, setPrice } = await fixtureDeployed();
+
+      const expiry = new Date();
+      setPrice(130, new Date(expiry.getTime() - 1000), ethFeedId);
+
+      await expect(
+        program.methods
+          .mark(
+            solFeedId,
+            new anchor.BN(Math.floor(expiry.getTime() / 1000))
+          )
+          .accounts({ priceUpdate })
+          .rpc()
+      ).rejects.toThrowError(/MismatchedFeedId/);
+    });
+
+    it("Can set mark price after expiry", async () => {
+      const { seller, program, context, provider, setPrice } =
+        await fixtureDeployed();
+
+      const expiry = new Date();
+      const publishTime = expiry;
+      setPrice(130, publishTime);
+
+      // Still within the challenge period
+      await warpTo(context, new BN(expiry.getTime() / 1000).add(new BN(100)));
+
+      await program.methods
+        .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .accounts({ priceUpdate })
+        .rpc();
+
+      expect(
+        await program.account.expiryData.fetch(
+          getExpiryPda({
+            expiry,
+            feedId: solFeedId,
+            programId: program.programId,
+          })
+        )
+      ).toStrictEqual({
+        bump: expect.any(Number),
+        conf: expect.toBeBN(new BN(12190053)),
+        payer: seller.publicKey,
+        price: expect.toBeBN(new BN(13000000000)),
+        publishTime: expect.toBeBN(
+          new BN(Math.floor(publishTime.getTime() / 1000))
+        ),
+        exponent: -8,
+        feedId: solFeedId,
+        refCount: expect.toBeBN(new BN(0)),
+        twapCount: 1,
+        twapFirstPublishTime: expect.toBeBN(
+          new BN(Math.floor(publishTime.getTime() / 1000))
+        ),
+        twapSum: expect.toBeBN(new BN(0)),
+      });
+    });
+
+    it("Can reject mark after the challenge period", async () => {
+      const { program, context, setPrice } = await fixtureDeployed();
+
+      const expiry = new Date();
+      setPrice(130, expiry);
+
+      await warpTo(
+        context,
+        new BN(expiry.getTime() / 1000).add(new BN(challengePeriod))
+      );
+
+      await expect(
+        program.methods
+          .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+          .accounts({ priceUpdate })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d+. Error Code: MarkFinalized. Error Number: 6025. Error Message: Mark is final and can no longer be updated./
+      );
+    });
+
+    it("Can set mark price", async () => {
+      const { program, setPrice, seller } = await fixtureDeployed();
+
+      const publishTime = new Date(Date.now() - 1000);
+      const expiry = new Date();
+      setPrice(130, publishTime);
+
+      await program.methods
+        .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .accounts({ priceUpdate })
+        .rpc();
+
+      expect(
+        await program.account.expiryData.fetch(
+          getExpiryPda({
+            expiry,
+            feedId: solFeedId,
+            programId: program.programId,
+          })
+        )
+      ).toStrictEqual({
+        bump: expect.any(Number),
+        conf: expect.toBeBN(new BN(12190053)),
+        payer: seller.publicKey,
+        price: expect.toBeBN(new BN(13000000000)),
+        publishTime: expect.toBeBN(
+          new BN(Math.floor(publishTime.getTime() / 1000))
+        ),
+        exponent: -8,
+        feedId: solFeedId,
+        refCount: expect.toBeBN(new BN(0)),
+        twapCount: 1,
+        twapFirstPublishTime: expect.toBeBN(
+          new BN(Math.floor(publishTime.getTime() / 1000))
+        ),
+        twapSum: expect.toBeBN(new BN(0)),
+      });
+    });
+
+    it("Can reject if price confidence is too wide", async () => {
+      const { program, setPrice } = await fixtureDeployed();
+
+      const expiry = new Date();
+      // 3% of the price
+      setPrice(130, new Date(expiry.getTime() - 1000), solFeedId, 390000000);
+
+      await expect(
+        program.methods
+          .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+          .accounts({ priceUpdate })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d+. Error Code: ConfidenceTooWide. Error Number: 6021. Error Message: Price confidence interval is too wide./
+      );
+    });
+
+    it("Can accept a wider confidence once the config allows it", async () => {
+      const { program, payer, setPrice } = await fixtureDeployed();
+
+      await program.methods
+        .updateConfig({
+          feeBpsPremium: 0,
+          feeBpsSettlement: 0,
+          markWindow: new anchor.BN(1800),
+          maxConfBps: 400,
+          allowedFeeds: [],
+          isWritePaused: false,
+          isBuyPaused: false,
+          isMarkPaused: false,
+        })
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      const expiry = new Date();
+      // 3% of the price
+      setPrice(130, new Date(expiry.getTime() - 1000), solFeedId, 390000000);
+
+      await program.methods
+        .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .accounts({ priceUpdate })
+        .rpc();
+    });
+
+    it("Can reject if price is after expiry", async () => {
+      const { program, setPrice } = await fixtureDeployed();
+
+      const expiry = new Date();
+      const publishTime = new Date(expiry.getTime() + 1000);
+      setPrice(130, publishTime);
+
+      await expect(
+        program.methods
+          .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+          .accounts({ priceUpdate })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d+. Error Code: PriceIrrelevant. Error Number: 6007. Error Message: Price not close to expiry./
+      );
+    });
+
+    it("Can reject update if mark price is further away", async () => {
+      const { program, context, provider, setPrice } = await fixtureDeployed();
+
+      const expiry = new Date();
+
+      setPrice(130, new Date(expiry.getTime() - 1000));
+      await program.methods
+        .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .accounts({ priceUpdate })
+        .rpc();
+
+      const publishTime = new Date(expiry.getTime() - 2000);
+      setPrice(131, publishTime);
+
+      await new Promise((resolve) => setTimeout(resolve, 3));
+
+      await expect(
+        program.methods
+          .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+          .accounts({ priceUpdate })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d+. Error Code: PriceIrrelevant. Error Number: 6007. Error Message: Price not close to expiry./
+      );
+    });
+
+    it("Can update if mark price is closer", async () => {
+      const { program, setPrice, seller } = await fixtureDeployed();
+
+      const expiry = new Date();
+
+      setPrice(130, new Date(expiry.getTime() - 2000));
+      await program.methods
+        .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .accounts({ priceUpdate })
+        .rpc();
+
+      await new Promise((resolve) => setTimeout(resolve, 3));
+
+      const publishTime = new Date(expiry.getTime() - 1000);
+      setPrice(131, publishTime);
+      await program.methods
+        .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .accounts({ priceUpdate })
+        .rpc();
+
+      expect(
+        await program.account.expiryData.fetch(
+          getExpiryPda({
+            expiry,
+            feedId: solFeedId,
+            programId: program.programId,
+          })
+        )
+      ).toStrictEqual({
+        bump: expect.any(Number),
+        conf: expect.toBeBN(new BN(12190053)),
+        payer: seller.publicKey,
+        price: expect.toBeBN(new BN(13100000000)),
+        publishTime: expect.toBeBN(
+          new BN(Math.floor(publishTime.getTime() / 1000))
+        ),
+        exponent: -8,
+        feedId: solFeedId,
+        refCount: expect.toBeBN(new BN(0)),
+        // The first print is weighted by the second up to the newer one
+        twapCount: 2,
+        twapFirstPublishTime: expect.toBeBN(
+          new BN(Math.floor(publishTime.getTime() / 1000) - 1)
+        ),
+        twapSum: expect.toBeBN(new BN(13000000000)),
+      });
+    });
+
+    it("Can reject closing mark while a bought option needs it", async () => {
+      const { program, buyer, setPrice, expiry } = await fixtureBought();
+
+      // The buyer paid for the expiry account when buying
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+
+      await expect(
+        program.methods
+          .markClose(solFeedId, expiry)
+          .accounts({ payer: buyer.publicKey })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark_close.rs:\d+. Error Code: MarkInUse. Error Number: 6028. Error Message: Mark is still needed by open options./
+      );
+    });
+
+    it("Can close mark price once its options have settled", async () => {
+      const { program, buyer, context, expiry } = await fixtureExercised();
+
+      await program.methods
+        .markClose(solFeedId, expiry)
+        .accounts({ payer: buyer.publicKey })
+        .signers([buyer])
+        .rpc();
+
+      expect(
+        await context.banksClient.getAccount(
+          getExpiryPda({
+            expiry: new Date(expiry.toNumber() * 1000),
+            feedId: solFeedId,
+            programId: program.programId,
+          })
+        )
+      ).to.equal(null);
+    });
+
+    it("Can close mark price", async () => {
+      const { program, setPrice, context } = await fixtureDeployed();
+
+      const expiry = new Date();
+      setPrice(130, expiry);
+
+      await program.methods
+        .mark(solFeedId, new anchor.BN(Math.floor(expiry.getTime() / 1000)))
+        .accounts({ priceUpdate })
+        .rpc();
+
+      await program.methods
+        .markClose(
+          solFeedId,
+          new anchor.BN(Math.floor(expiry.getTime() / 1000))
+        )
+        .rpc();
+
+      expect(
+        await context.banksClient.getAccount(
+          getExpiryPda({
+            expiry,
+            feedId: solFeedId,
+            programId: program.programId,
+          })
+        )
+      ).to.equal(null);
+    });
+  });
+
+  describe("Config", () => {
+    const params = {
+      feeBpsPremium: 50,
+      feeBpsSettlement: 25,
+      markWindow: new anchor.BN(600),
+      maxConfBps: 200,
+      allowedFeeds: [solFeedId],
+      isWritePaused: false,
+      isBuyPaused: false,
+      isMarkPaused: false,
+    };
+
+    it("Can initialize config with defaults", async () => {
+      const { program, payer } = await fixtureDeployed();
+
+      expect(
+        await program.account.config.fetch(getConfigPda(program.programId))
+      ).toStrictEqual({
+        admin: payer.publicKey,
+        allowedFeeds: [],
+        bump: expect.any(Number),
+        feeBpsPremium: 0,
+        feeBpsSettlement: 0,
+        isBuyPaused: false,
+        isMarkPaused: false,
+        isWritePaused: false,
+        markWindow: expect.toBeBN(new anchor.BN(1800)),
+        maxConfBps: 200,
+        pendingAdmin: null,
+      });
+    });
+
+    it("Can reject config initialization by anyone else", async () => {
+      const context = await startAnchor(".", [], []);
+      const seller = Keypair.generate();
+      await airdrop(context, seller.publicKey, 1 * LAMPORTS_PER_SOL);
+      const provider = new BankrunProvider(context, new anchor.Wallet(seller));
+      // @ts-ignore
+      const program = new Program<SolanaOptions>(IDL, provider);
+      setUpgradeAuthority(context, program.programId, context.payer.publicKey);
+
+      await expect(
+        program.methods
+          .initializeConfig(seller.publicKey)
+          .accounts({
+            payer: seller.publicKey,
+            programData: getProgramDataPda(program.programId),
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: program_data. Error Code: NotUpgradeAuthority. Error Number: 6037. Error Message: Signer is not the program upgrade authority."
+      );
+    });
+
+    it("Can update config by admin", async () => {
+      const { program, payer } = await fixtureDeployed();
+
+      await program.methods
+        .updateConfig(params)
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      const config = await program.account.config.fetch(
+        getConfigPda(program.programId)
+      );
+      expect(config.feeBpsPremium).to.equal(50);
+      expect(config.feeBpsSettlement).to.equal(25);
+      expect(config.markWindow).toBeBN(new anchor.BN(600));
+      expect(config.allowedFeeds).toStrictEqual([solFeedId]);
+    });
+
+    it("Can reject config update by anyone else", async () => {
+      const { program, seller } = await fixtureDeployed();
+
+      await expect(
+        program.methods
+          .updateConfig(params)
+          .accounts({ admin: seller.publicKey })
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: config. Error Code: ConstraintHasOne. Error Number: 2001. Error Message: A has one constraint was violated."
+      );
+    });
+
+    it("Can reject a fee above the maximum", async () => {
+      const { program, payer } = await fixtureDeployed();
+
+      await expect(
+        program.methods
+          .updateConfig({ ...params, feeBpsPremium: 1001 })
+          .accounts({ admin: payer.publicKey })
+          .signers([payer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/update_config.rs:\d+. Error Code: InvalidFee. Error Number: 6029. Error Message: Fee is above the maximum./
+      );
+    });
+
+    it("Can reject options on a feed that is not allowed", async () => {
+      const { program, payer, wsol, usdc, buyer } = await fixtureDeployed();
+
+      await program.methods
+        .updateConfig(params)
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      await expect(
+        program.methods
+          .initialize(
+            new anchor.BN("1000"),
+            new anchor.BN("3500"),
+            new anchor.BN(expiry),
+            ethFeedId,
+            new anchor.BN(10),
+            false,
+            { cash: {} },
+            { european: {} },
+            new anchor.BN(1),
+            200,
+            new anchor.BN(0),
+            { mark: {} }
+          )
+          .accounts({
+            mintBase: wsol,
+            mintPremium: wsol,
+            mintQuote: usdc,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/initialize.rs:\d+. Error Code: FeedNotAllowed. Error Number: 6031. Error Message: Feed is not allowed./
+      );
+    });
+
+    it("Can hand over admin in two steps", async () => {
+      const { program, payer, seller, buyer } = await fixtureDeployed();
+      const config = getConfigPda(program.programId);
+
+      await program.methods
+        .transferAdmin(seller.publicKey)
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      // Still the admin until the handover is accepted
+      expect((await program.account.config.fetch(config)).admin).toStrictEqual(
+        payer.publicKey
+      );
+
+      await expect(
+        program.methods
+          .acceptAdmin()
+          .accounts({ pendingAdmin: buyer.publicKey })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: config. Error Code: NotPendingAdmin. Error Number: 6032. Error Message: Signer is not the pending admin."
+      );
+
+      await program.methods
+        .acceptAdmin()
+        .accounts({ pendingAdmin: seller.publicKey })
+        .rpc();
+
+      const { admin, pendingAdmin } =
+        await program.account.config.fetch(config);
+      expect(admin).toStrictEqual(seller.publicKey);
+      expect(pendingAdmin).to.equal(null);
+    });
+
+    it("Can take protocol fees into the treasury", async () => {
+      const {
+        program,
+        payer,
+        pda,
+        buyer,
+        seller,
+        wsol,
+        usdc,
+        context,
+        setPrice,
+        expiry,
+      } = await fixtureInitialized();
+      const config = getConfigPda(program.programId);
+
+      await program.methods
+        .updateConfig({
+          ...params,
+          feeBpsPremium: 1000,
+          feeBpsSettlement: 1000,
+          allowedFeeds: [],
+        })
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      await program.methods
+        .buy(new anchor.BN(10))
+        .accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // 10% of the premium goes to the treasury
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, config)
+      ).to.equal(BigInt(1));
+      expect(await getAtaTokenBalance(context.banksClient, wsol, pda)).to.equal(
+        BigInt(1009)
+      );
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+      await program.methods
+        .exercise(new anchor.BN(1))
+        .accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([buyer])
+        .rpc();
+
+      // 10% of the settlement goes to the treasury
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(990 + 113));
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, config)
+      ).to.equal(BigInt(1 + 12));
+
+      await expect(
+        program.methods
+          .withdrawTreasury(new anchor.BN(13))
+          .accounts({
+            admin: seller.publicKey,
+            mint: wsol,
+            destination: token.getAssociatedTokenAddressSync(
+              wsol,
+              seller.publicKey
+            ),
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        "AnchorError caused by account: config. Error Code: ConstraintHasOne. Error Number: 2001. Error Message: A has one constraint was violated."
+      );
+
+      await program.methods
+        .withdrawTreasury(new anchor.BN(13))
+        .accounts({
+          admin: payer.publicKey,
+          mint: wsol,
+          destination: token.getAssociatedTokenAddressSync(
+            wsol,
+            buyer.publicKey
+          ),
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([payer])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, config)
+      ).to.equal(BigInt(0));
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, buyer.publicKey)
+      ).to.equal(BigInt(990 + 113 + 13));
+    });
+
+    it("Can pause writes, purchases and marks but still close", async () => {
+      const {
+        program,
+        payer,
+        pda,
+        buyer,
+        seller,
+        wsol,
+        usdc,
+        context,
+        expiry,
+      } = await fixtureInitialized();
+
+      await program.methods
+        .updateConfig({
+          ...params,
+          allowedFeeds: [],
+          isWritePaused: true,
+          isBuyPaused: true,
+          isMarkPaused: true,
+        })
+        .accounts({ admin: payer.publicKey })
+        .signers([payer])
+        .rpc();
+
+      await expect(
+        program.methods
+          .buy(new anchor.BN(10))
+          .accounts({
+            data: pda,
+            buyer: buyer.publicKey,
+            mintPremium: wsol,
+            payer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([buyer])
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/buy.rs:\d+. Error Code: Paused. Error Number: 6034. Error Message: Instruction is paused./
+      );
+
+      await expect(
+        program.methods
+          .mark(solFeedId, expiry)
+          .accounts({ priceUpdate })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/mark.rs:\d+. Error Code: Paused. Error Number: 6034. Error Message: Instruction is paused./
+      );
+
+      await expect(
+        program.methods
+          .initialize(
+            new anchor.BN("1000"),
+            new anchor.BN("3500"),
+            expiry.add(new anchor.BN(60)),
+            solFeedId,
+            new anchor.BN(10),
+            false,
+            { cash: {} },
+            { european: {} },
+            new anchor.BN(1),
+            200,
+            new anchor.BN(0),
+            { mark: {} }
+          )
+          .accounts({
+            mintBase: wsol,
+            mintPremium: wsol,
+            mintQuote: usdc,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          })
+          .rpc()
+      ).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/initialize.rs:\d+. Error Code: Paused. Error Number: 6034. Error Message: Instruction is paused./
+      );
+
+      // Sellers can always take their collateral back
+      await program.methods
+        .close()
+        .accounts({
+          mintBase: wsol,
+          data: pda,
+          seller: seller.publicKey,
+          expiry: null,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        })
+        .signers([seller])
+        .rpc();
+
+      expect(
+        await getAtaTokenBalance(context.banksClient, wsol, seller.publicKey)
+      ).to.equal(BigInt(1000));
+    });
+
+    it("Can hold off lapsing while marks are paused", async () => {
+      const { program, payer, pda, seller, wsol, context, expiry } =
+        await fixtureBought();
+      const pauseMarks = (isMarkPaused: boolean) =>
+        program.methods
+          .updateConfig({ ...params, allowedFeeds: [], isMarkPaused })
+          .accounts({ admin: payer.publicKey })
+          .signers([payer])
+          .rpc();
+      const close = () =>
+        program.methods
+          .close()
+          .accounts({
+            mintBase: wsol,
+            data: pda,
+            seller: seller.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+            tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+          })
+          .signers([seller])
+          .rpc();
+
+      // Nobody could mark the expiry through the grace period
+      await pauseMarks(true);
+      await warpTo(context, expiry.add(new anchor.BN(3 * 24 * 60 * 60)));
+
+      await expect(close()).rejects.toThrowError(
+        /AnchorError thrown in programs\/solana-options\/src\/instructions\/close.rs:\d+. Error Code: OptionCannotBeClosedYet. Error Number: 6004. Error Message: Option cannot be closed Yet./
+      );
+
+      await pauseMarks(false);
+      await close();
+
+      expect(await context.banksClient.getAccount(pda)).to.equal(null);
+    });
+  });
+
+  describe("Events", () => {
+    it("Can follow an option through its events", async () => {
+      const { context, program, wsol, usdc, seller, buyer, setPrice } =
+        await fixtureDeployed();
+      const expiry = new anchor.BN(Math.floor(Date.now() / 1000) + 180);
+      const pda = getPda({
+        amountQuote: 3500n,
+        amountBase: 1000n,
+        expiry: BigInt(expiry.toString()),
+        mintQuote: usdc,
+        mintBase: wsol,
+        programId: program.programId,
+        seller: seller.publicKey,
+      });
+      const strike = new BN(3500 * 10 ** 8);
+
+      const [created] = await getEvents(
+        context,
+        program,
+        program.methods
+          .initialize(
+            new anchor.BN("1000"),
+            new anchor.BN("3500"),
+            expiry,
+            solFeedId,
+            new anchor.BN(10),
+            false,
+            { cash: {} },
+            { european: {} },
+            new anchor.BN(1),
+            200,
+            new anchor.BN(0),
+            { mark: {} }
+          )
+          .accounts({
+            mintBase: wsol,
+            mintPremium: wsol,
+            mintQuote: usdc,
+            buyer: buyer.publicKey,
+            tokenProgram: token.TOKEN_PROGRAM_ID,
+          }),
+        [seller]
+      );
+      expect(created.name).to.equal("optionCreated");
+      expect(created.data).toMatchObject({
+        option: pda,
+        kind: { call: {} },
+        seller: seller.publicKey,
+        buyer: buyer.publicKey,
+        amountBase: expect.toBeBN(new BN(1000)),
+        amountQuote: expect.toBeBN(new BN(3500)),
+        amountPremiumAsk: expect.toBeBN(new BN(10)),
+        strike: expect.toBeBN(strike),
+        timestampExpiry: expect.toBeBN(expiry),
+      });
+
+      const [bought] = await getEvents(
+        context,
+        program,
+        program.methods.buy(new anchor.BN(10)).accounts({
+          data: pda,
+          buyer: buyer.publicKey,
+          mintPremium: wsol,
+          payer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        }),
+        [buyer]
+      );
+      expect(bought.name).to.equal("optionBought");
+      expect(bought.data).toMatchObject({
+        option: pda,
+        buyer: buyer.publicKey,
+        payer: buyer.publicKey,
+        amountPremium: expect.toBeBN(new BN(10)),
+        amountFee: expect.toBeBN(new BN(0)),
+        isPremiumToSeller: false,
+      });
+
+      setPrice(4000);
+      await program.methods
+        .mark(solFeedId, expiry)
+        .accounts({ priceUpdate })
+        .rpc();
+      await warpTo(context, expiry.add(new anchor.BN(challengePeriod)));
+      await fundAtaAccount(context.banksClient, usdc, buyer, BigInt(3500));
+
+      const [exercised] = await getEvents(
+        context,
+        program,
+        program.methods.exercise(new anchor.BN(1)).accounts({
+          mintBase: wsol,
+          mintQuote: usdc,
+          data: pda,
+          buyer: buyer.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+        }),
+        [buyer]
+      );
+      expect(exercised.name).to.equal("optionExercised");
+      expect(exercised.data).toMatchObject({
+        option: pda,
+        buyer: buyer.publicKey,
+        strike: expect.toBeBN(strike),
+        price: expect.toBeBN(new BN(4000 * 10 ** 8)),
+        contracts: expect.toBeBN(new BN(1)),
+        amountSeller: expect.toBeBN(new BN(875)),
+        amountBuyer: expect.toBeBN(new BN(125)),
+        amountFee: expect.toBeBN(new BN(0)),
+        amountQuotePaid: expect.toBeBN(new BN(0)),
+      });
+
+      const [closed] = await getEvents(
+        context,
+        program,
+        program.methods.close().accounts({
+          mintBase: wsol,
+          data: pda,
+          seller: seller.publicKey,
+          tokenProgram: token.TOKEN_PROGRAM_ID,
+          tokenProgramPremium: token.TOKEN_PROGRAM_ID,
+        }),
+        [seller]
+      );
+      expect(closed.name).to.equal("optionClosed");
+      expect(closed.data).toMatchObject({
+        option: pda,
+        seller: seller.publicKey,
+        // Remaining collateral and the premium share the base vault
+        amountCollateral: expect.toBeBN(new BN(885)),
+        isLapsed: false,
+      });
     });
   });
 });